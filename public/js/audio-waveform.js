@@ -0,0 +1,79 @@
+// Clickable waveform above the audio player, drawn from the peaks served by /waveform
+(function () {
+    const container = document.getElementById('audio-waveform');
+    const audio = document.getElementById('main-audio');
+    if (!container || !audio) {
+        return;
+    }
+
+    const canvas = container.querySelector('canvas');
+    const context = canvas.getContext('2d');
+    const PLAYED = '#38bdf8';
+    const REMAINING = '#475569';
+    let waveform = null;
+
+    function duration() {
+        return isFinite(audio.duration) && audio.duration > 0 ? audio.duration : waveform.duration;
+    }
+
+    function draw() {
+        if (!waveform) {
+            return;
+        }
+
+        const ratio = window.devicePixelRatio || 1;
+        const width = Math.round(canvas.clientWidth * ratio);
+        const height = Math.round(canvas.clientHeight * ratio);
+        if (canvas.width !== width || canvas.height !== height) {
+            canvas.width = width;
+            canvas.height = height;
+        }
+        context.clearRect(0, 0, width, height);
+
+        const peaks = waveform.peaks;
+        const bar = Math.max(1, Math.round(2 * ratio));
+        const step = bar + Math.max(1, Math.round(ratio));
+        const middle = height / 2;
+        const playedX = duration() > 0 ? (audio.currentTime / duration()) * width : 0;
+
+        for (let x = 0; x < width; x += step) {
+            // Loudest peak under this bar
+            const start = Math.floor((x / width) * peaks.length);
+            const end = Math.max(start + 1, Math.floor(((x + step) / width) * peaks.length));
+            let peak = 0;
+            for (let i = start; i < end && i < peaks.length; i++) {
+                peak = Math.max(peak, peaks[i]);
+            }
+
+            const half = Math.max(ratio / 2, (peak / 255) * (middle - ratio));
+            context.fillStyle = x < playedX ? PLAYED : REMAINING;
+            context.fillRect(x, middle - half, bar, half * 2);
+        }
+    }
+
+    canvas.addEventListener('click', event => {
+        if (!waveform || duration() <= 0) {
+            return;
+        }
+        const rect = canvas.getBoundingClientRect();
+        const fraction = Math.min(1, Math.max(0, (event.clientX - rect.left) / rect.width));
+        audio.currentTime = fraction * duration();
+        draw();
+    });
+
+    audio.addEventListener('timeupdate', draw);
+    audio.addEventListener('seeked', draw);
+    window.addEventListener('resize', draw);
+
+    fetch(`/waveform/${container.dataset.waveformPath}`)
+        .then(response => (response.ok ? response.json() : null))
+        .then(data => {
+            if (!data || data.peaks.length === 0) {
+                return;
+            }
+            waveform = data;
+            container.classList.remove('hidden');
+            draw();
+        })
+        .catch(error => console.warn('Falha ao carregar a forma de onda:', error));
+})();
@@ -0,0 +1,98 @@
+// Paint BlurHash placeholders behind thumbnails while they load
+(function () {
+    const images = document.querySelectorAll('img[data-blurhash]');
+    if (!images.length) return;
+
+    const CHARS = '0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~';
+    const SIZE = 32;
+
+    const decode83 = (str) => {
+        let value = 0;
+        for (const c of str) {
+            value = value * 83 + CHARS.indexOf(c);
+        }
+        return value;
+    };
+
+    const srgbToLinear = (value) => {
+        const v = value / 255;
+        return v <= 0.04045 ? v / 12.92 : Math.pow((v + 0.055) / 1.055, 2.4);
+    };
+
+    const linearToSrgb = (value) => {
+        const v = Math.max(0, Math.min(1, value));
+        return v <= 0.0031308
+            ? Math.round(v * 12.92 * 255 + 0.5)
+            : Math.round((1.055 * Math.pow(v, 1 / 2.4) - 0.055) * 255 + 0.5);
+    };
+
+    const signPow = (value, exp) => Math.sign(value) * Math.pow(Math.abs(value), exp);
+
+    function decode(hash, width, height) {
+        const sizeFlag = decode83(hash[0]);
+        const numY = Math.floor(sizeFlag / 9) + 1;
+        const numX = (sizeFlag % 9) + 1;
+        const maxValue = (decode83(hash[1]) + 1) / 166;
+
+        const colors = [];
+        const dc = decode83(hash.substring(2, 6));
+        colors.push([srgbToLinear(dc >> 16), srgbToLinear((dc >> 8) & 255), srgbToLinear(dc & 255)]);
+
+        for (let i = 1; i < numX * numY; i++) {
+            const ac = decode83(hash.substring(4 + i * 2, 6 + i * 2));
+            const r = Math.floor(ac / (19 * 19));
+            const g = Math.floor(ac / 19) % 19;
+            const b = ac % 19;
+            colors.push([
+                signPow((r - 9) / 9, 2) * maxValue,
+                signPow((g - 9) / 9, 2) * maxValue,
+                signPow((b - 9) / 9, 2) * maxValue,
+            ]);
+        }
+
+        const pixels = new Uint8ClampedArray(width * height * 4);
+        for (let y = 0; y < height; y++) {
+            for (let x = 0; x < width; x++) {
+                let r = 0, g = 0, b = 0;
+                for (let j = 0; j < numY; j++) {
+                    for (let i = 0; i < numX; i++) {
+                        const basis = Math.cos((Math.PI * x * i) / width) * Math.cos((Math.PI * y * j) / height);
+                        const color = colors[i + j * numX];
+                        r += color[0] * basis;
+                        g += color[1] * basis;
+                        b += color[2] * basis;
+                    }
+                }
+                const offset = 4 * (x + y * width);
+                pixels[offset] = linearToSrgb(r);
+                pixels[offset + 1] = linearToSrgb(g);
+                pixels[offset + 2] = linearToSrgb(b);
+                pixels[offset + 3] = 255;
+            }
+        }
+        return pixels;
+    }
+
+    const canvas = document.createElement('canvas');
+    canvas.width = SIZE;
+    canvas.height = SIZE;
+    const ctx = canvas.getContext('2d');
+
+    images.forEach(img => {
+        if (img.complete && img.naturalWidth > 0) return;
+
+        try {
+            const pixels = decode(img.dataset.blurhash, SIZE, SIZE);
+            ctx.putImageData(new ImageData(pixels, SIZE, SIZE), 0, 0);
+            img.style.backgroundImage = `url(${canvas.toDataURL()})`;
+            img.style.backgroundSize = 'cover';
+        } catch (error) {
+            console.warn('Invalid blurhash:', img.dataset.blurhash, error);
+            return;
+        }
+
+        img.addEventListener('load', () => {
+            img.style.backgroundImage = '';
+        }, { once: true });
+    });
+})();
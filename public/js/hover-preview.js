@@ -0,0 +1,53 @@
+// Animated previews for video tiles in grid view
+(function () {
+    const tiles = document.querySelectorAll('[data-hover-src]');
+    if (!tiles.length) return;
+
+    // Wait a moment so sweeping the mouse across the grid doesn't start every preview
+    const HOVER_DELAY = 300;
+
+    tiles.forEach(tile => {
+        let video = null;
+        let timer = null;
+
+        const show = () => {
+            if (!video) {
+                video = document.createElement('video');
+                video.src = tile.dataset.hoverSrc;
+                video.muted = true;
+                video.loop = true;
+                video.playsInline = true;
+                video.preload = 'auto';
+                video.className = 'absolute inset-0 h-full w-full rounded-lg object-cover object-center';
+                video.style.opacity = '0';
+                video.style.transition = 'opacity 150ms';
+                video.addEventListener('playing', () => {
+                    video.style.opacity = '1';
+                });
+                video.addEventListener('error', () => {
+                    // No preview available: keep the static thumbnail
+                    video.remove();
+                    video = null;
+                    tile.removeAttribute('data-hover-src');
+                });
+                tile.appendChild(video);
+            }
+
+            video.currentTime = 0;
+            video.play().catch(() => {});
+        };
+
+        tile.addEventListener('mouseenter', () => {
+            if (!tile.dataset.hoverSrc) return;
+            timer = setTimeout(show, HOVER_DELAY);
+        });
+
+        tile.addEventListener('mouseleave', () => {
+            clearTimeout(timer);
+            if (video) {
+                video.pause();
+                video.style.opacity = '0';
+            }
+        });
+    });
+})();
@@ -0,0 +1,59 @@
+// Fill in duration and resolution of listed files that were not probed yet
+(function () {
+    const pending = Array.from(document.querySelectorAll('[data-summary-path]'));
+    if (pending.length === 0) {
+        return;
+    }
+
+    // Probing runs ffprobe on the server, keep a few requests in flight at most
+    const CONCURRENCY = 3;
+
+    function formatDuration(seconds) {
+        const total = Math.round(seconds);
+        const hours = Math.floor(total / 3600);
+        const minutes = Math.floor((total % 3600) / 60);
+        const secs = total % 60;
+
+        if (hours > 0) {
+            return `${hours}:${minutes.toString().padStart(2, '0')}:${secs.toString().padStart(2, '0')}`;
+        }
+        return `${minutes}:${secs.toString().padStart(2, '0')}`;
+    }
+
+    function fill(element, summary) {
+        const values = {
+            duration: summary && summary.duration != null ? formatDuration(summary.duration) : null,
+            resolution: summary && summary.width && summary.height ? `${summary.width}×${summary.height}` : null,
+        };
+
+        element.querySelectorAll('[data-summary]').forEach(field => {
+            const value = values[field.dataset.summary];
+            if (field.hasAttribute('data-summary-badge')) {
+                // Badges stay hidden when there is nothing to show
+                if (value) {
+                    field.textContent = value;
+                    field.classList.remove('hidden');
+                }
+            } else {
+                field.textContent = value || '-';
+            }
+        });
+    }
+
+    async function worker() {
+        while (pending.length > 0) {
+            const element = pending.shift();
+            try {
+                const response = await fetch(`/meta/${element.dataset.summaryPath}?summary=1`);
+                fill(element, response.ok ? await response.json() : null);
+            } catch (error) {
+                console.warn('Erro ao carregar detalhes de mídia:', error);
+                fill(element, null);
+            }
+        }
+    }
+
+    for (let i = 0; i < CONCURRENCY; i++) {
+        worker();
+    }
+})();
@@ -0,0 +1,54 @@
+// Album player: "Tocar tudo" queues the tracks in disc/track order, a click on a track starts from it
+(function () {
+    const audio = document.getElementById('music-player');
+    const list = document.getElementById('music-tracks');
+    if (!audio || !list) {
+        return;
+    }
+
+    const tracks = Array.from(list.querySelectorAll('[data-track-src]'));
+    const playAll = document.getElementById('music-play-all');
+    let current = -1;
+
+    function highlight() {
+        tracks.forEach((track, index) => {
+            const label = track.querySelector('[data-track-label]');
+            track.classList.toggle('bg-slate-900/80', index === current);
+            if (label) {
+                label.classList.toggle('text-emerald-200', index === current);
+                label.classList.toggle('text-slate-100', index !== current);
+            }
+        });
+    }
+
+    function play(index) {
+        if (index < 0 || index >= tracks.length) {
+            current = -1;
+            highlight();
+            return;
+        }
+
+        current = index;
+        const track = tracks[index];
+        audio.src = track.dataset.trackSrc;
+        document.title = `${track.dataset.trackTitle} - Media Serve`;
+        highlight();
+        audio.play().catch(() => {});
+    }
+
+    audio.addEventListener('ended', () => play(current + 1));
+
+    tracks.forEach((track, index) => {
+        track.addEventListener('click', (event) => {
+            // The link to the file page keeps its own behavior
+            if (event.target.closest('a')) {
+                return;
+            }
+            play(index);
+        });
+    });
+
+    if (playAll) {
+        playAll.addEventListener('click', () => play(0));
+    }
+})();
@@ -0,0 +1,67 @@
+// Report the playback position to the server and resume where the last session stopped
+(function () {
+    const player = document.querySelector('[data-progress-path]');
+    if (!player) {
+        return;
+    }
+
+    const REPORT_INTERVAL = 10000;
+    const url = `/progress/${player.dataset.progressPath}`;
+    let lastReported = null;
+
+    const resumeAt = parseFloat(player.dataset.resume);
+    if (resumeAt > 0) {
+        const resume = () => {
+            // Too close to the end, let it start over
+            if (!isFinite(player.duration) || resumeAt < player.duration - 5) {
+                player.currentTime = resumeAt;
+            }
+        };
+        if (player.readyState >= 1) {
+            resume();
+        } else {
+            player.addEventListener('loadedmetadata', resume, { once: true });
+        }
+    }
+
+    function payload() {
+        return JSON.stringify({
+            position: player.currentTime,
+            duration: isFinite(player.duration) ? player.duration : 0,
+        });
+    }
+
+    function report() {
+        if (!player.currentTime || player.currentTime === lastReported) {
+            return;
+        }
+        lastReported = player.currentTime;
+        fetch(url, {
+            method: 'POST',
+            headers: { 'Content-Type': 'application/json' },
+            body: payload(),
+            keepalive: true,
+        }).catch(error => console.warn('Erro ao salvar posição de reprodução:', error));
+    }
+
+    setInterval(() => {
+        if (!player.paused) {
+            report();
+        }
+    }, REPORT_INTERVAL);
+
+    player.addEventListener('pause', report);
+    player.addEventListener('ended', report);
+    player.addEventListener('seeked', () => {
+        if (player.paused) {
+            report();
+        }
+    });
+
+    // Closing the tab does not wait for fetch, a beacon still gets through
+    window.addEventListener('pagehide', () => {
+        if (player.currentTime && player.currentTime !== lastReported) {
+            navigator.sendBeacon(url, new Blob([payload()], { type: 'application/json' }));
+        }
+    });
+})();
@@ -32,6 +32,18 @@
         pathEl.textContent = item.rel_path;
 
         left.appendChild(nameEl);
+
+        // Audio files show who and what they are, they may have matched on these tags
+        if (item.tags) {
+            const details = [item.tags.artist, item.tags.title, item.tags.album].filter(Boolean);
+            if (details.length > 0) {
+                const tagsEl = document.createElement('div');
+                tagsEl.className = 'truncate text-xs text-emerald-200';
+                tagsEl.textContent = details.join(' — ');
+                left.appendChild(tagsEl);
+            }
+        }
+
         left.appendChild(pathEl);
 
         const right = document.createElement('div');
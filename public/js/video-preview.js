@@ -10,7 +10,9 @@
 
     let previewInfo = null;
     let thumbnailsLoaded = false;
+    let thumbnailsStarted = false;
     let currentActiveThumbnail = null;
+    let chaptersRendered = false;
 
     const revealThumbnailsBar = () => {
         thumbnailsBar.classList.remove('hidden');
@@ -81,7 +83,9 @@
 
     // Update active thumbnail when video time changes
     video.addEventListener('timeupdate', function () {
-        if (!previewInfo || !thumbnailsLoaded) return;
+        if (!previewInfo) return;
+        updateActiveChapter(video.currentTime);
+        if (!thumbnailsLoaded) return;
         updateActiveThumbnail(video.currentTime);
     });
 
@@ -100,24 +104,22 @@
 
         previewInfo = await response.json();
         console.log('Preview info loaded:', previewInfo);
+        renderChapters();
     }
 
     async function createThumbnailsBar() {
         console.log('Starting createThumbnailsBar...');
-        if (!previewInfo || thumbnailsLoaded) {
+        if (!previewInfo || thumbnailsLoaded || thumbnailsStarted) {
             console.log('Skipping createThumbnailsBar - previewInfo:', !!previewInfo, 'thumbnailsLoaded:', thumbnailsLoaded);
             return;
         }
-
-        // Use expected_count if available, otherwise estimate
-        const totalEstimated = previewInfo.expected_count || Math.max(1, Math.ceil(previewInfo.duration / previewInfo.interval));
-        console.log('Total estimated thumbnails:', totalEstimated);
+        thumbnailsStarted = true;
 
         // Container to track created thumbnails by time
         const createdTimes = new Set();
 
-        // Since thumbnails are already available (based on preview info), skip polling
-        if (previewInfo.thumbnails && previewInfo.thumbnails.length > 0) {
+        // Generation already finished, create everything at once
+        if (previewInfo.complete && previewInfo.thumbnails && previewInfo.thumbnails.length > 0) {
             console.log('Thumbnails already available, creating elements directly...');
 
             // Keep the loading state while creating thumbnails
@@ -144,64 +146,63 @@
             });
 
             thumbnailsLoaded = true;
+            renderScenes();
             console.log('All thumbnails loaded successfully');
             console.log('thumbnailsScroll children count:', thumbnailsScroll.children.length);
             console.log('thumbnailsBar visible:', !thumbnailsBar.classList.contains('hidden'));
             return;
-        }        // Fallback: Poll preview info until all thumbnails are generated (for live generation)
-        let lastCount = 0;
-        let pollAttempts = 0;
-        while (true) {
-            pollAttempts++;
-            console.log(`Poll attempt ${pollAttempts}, lastCount: ${lastCount}`);
-
-            try {
-                const resp = await fetch(`/video-previews/${videoPath}`);
-                if (resp.ok) {
-                    previewInfo = await resp.json();
-                    console.log(`Updated previewInfo: ${previewInfo.thumbnails.length} thumbnails available`);
+        }
+
+        // Live generation: follow the background job over SSE
+        const appendNewThumbnails = async () => {
+            const thumbnails = (previewInfo && previewInfo.thumbnails) || [];
+            if (thumbnails.length > 0 && !thumbnailsScroll.querySelector('.thumbnail-item')) {
+                // Remove loading UI
+                thumbnailsScroll.innerHTML = '';
+            }
+
+            for (const thumb of thumbnails) {
+                if (!createdTimes.has(String(thumb.time))) {
+                    createdTimes.add(String(thumb.time));
+                    const el = await createThumbnailElement(thumb);
+                    thumbnailsScroll.appendChild(el);
                 }
-            } catch (err) {
-                console.warn('Erro ao obter progresso das miniaturas:', err);
             }
+        };
 
-            const generated = (previewInfo && previewInfo.thumbnails) ? previewInfo.thumbnails.length : 0;
-            const total = previewInfo && previewInfo.expected_count ? previewInfo.expected_count : totalEstimated;
+        await appendNewThumbnails();
 
-            // Append any new thumbnails
-            if (generated > 0) {
-                // Ensure thumbnailsScroll contains the thumbnail elements (remove loading UI)
-                if (!thumbnailsScroll.querySelector('.thumbnail-item')) {
-                    thumbnailsScroll.innerHTML = '';
-                }
+        if (!previewInfo.complete) {
+            await new Promise(resolve => {
+                const events = new EventSource(`/video-previews/${videoPath}/events`);
 
-                for (const thumb of previewInfo.thumbnails) {
-                    if (!createdTimes.has(String(thumb.time))) {
-                        const el = await createThumbnailElement(thumb);
-                        thumbnailsScroll.appendChild(el);
-                        createdTimes.add(String(thumb.time));
+                const handle = async (event, done) => {
+                    try {
+                        previewInfo = JSON.parse(event.data);
+                    } catch (err) {
+                        console.warn('Erro ao ler progresso das miniaturas:', err);
+                        return;
                     }
-                }
-            }
-
-            // Stop polling if we've reached expected total or generation seems finished
-            if (generated >= total && total > 0) {
-                thumbnailsLoaded = true;
-                // remove loading overlay if any
-                // All thumbnails generated
-                break;
-            }
+                    await appendNewThumbnails();
+                    renderScenes();
+                    if (done) {
+                        events.close();
+                        resolve();
+                    }
+                };
 
-            // if no progress for a while, break to avoid infinite loop
-            if (generated == lastCount) {
-                // wait a bit and try again
-                await new Promise(r => setTimeout(r, 600));
-            } else {
-                lastCount = generated;
-                await new Promise(r => setTimeout(r, 300));
-            }
+                events.addEventListener('progress', event => handle(event, false));
+                events.addEventListener('complete', event => handle(event, true));
+                events.onerror = () => {
+                    // The browser reconnects on its own; reconnecting restarts a cancelled job
+                    console.warn('Conexão de progresso das miniaturas interrompida, reconectando...');
+                };
+            });
         }
 
+        thumbnailsLoaded = true;
+        renderScenes();
+
         // final cleanup: hide loading UI if thumbnails exist
         if (thumbnailsScroll.querySelector('.thumbnail-item')) {
             // nothing else to do; thumbnails are visible
@@ -215,22 +216,30 @@
         thumbnailDiv.className = 'thumbnail-item group flex w-32 flex-shrink-0 cursor-pointer flex-col gap-2 rounded-lg border border-slate-800/70 bg-slate-950/70 p-2 text-xs text-slate-300 transition hover:border-slate-600 hover:bg-slate-900/80';
         thumbnailDiv.dataset.time = thumbnail.time;
 
-        const img = document.createElement('img');
-        img.alt = `Preview em ${formatTime(thumbnail.time)}`;
-        img.className = 'h-20 w-full rounded-md object-cover object-center shadow-inner shadow-black/30';
-
         const timeLabel = document.createElement('div');
         timeLabel.className = 'thumbnail-time text-center font-medium text-slate-200 transition';
         timeLabel.textContent = formatTime(thumbnail.time);
 
-        thumbnailDiv.appendChild(img);
-        thumbnailDiv.appendChild(timeLabel);
-
         // Add click handler
         thumbnailDiv.addEventListener('click', function () {
             seekToTime(thumbnail.time);
         });
 
+        // Sprite sheets cover every frame with a couple of requests
+        const sheet = thumbnail.sprite && previewInfo.sprites ? previewInfo.sprites[thumbnail.sprite.sheet] : null;
+        if (sheet) {
+            thumbnailDiv.appendChild(createSpriteTile(thumbnail, sheet));
+            thumbnailDiv.appendChild(timeLabel);
+            return thumbnailDiv;
+        }
+
+        const img = document.createElement('img');
+        img.alt = `Preview em ${formatTime(thumbnail.time)}`;
+        img.className = 'h-20 w-full rounded-md object-cover object-center shadow-inner shadow-black/30';
+
+        thumbnailDiv.appendChild(img);
+        thumbnailDiv.appendChild(timeLabel);
+
         // Load thumbnail image
         try {
             console.log(`Fetching thumbnail image for time ${thumbnail.time}s...`);
@@ -258,6 +267,112 @@
         return thumbnailDiv;
     }
 
+    // Scene-cut thumbnails, present when the server runs with scene detection
+    function renderScenes() {
+        const scenesBar = document.getElementById('video-scenes-bar');
+        const scenesScroll = scenesBar ? scenesBar.querySelector('.scenes-scroll') : null;
+        const scenes = (previewInfo && previewInfo.scenes) || [];
+        if (!scenesScroll || scenes.length === 0) return;
+
+        for (let index = scenesScroll.children.length; index < scenes.length; index++) {
+            const scene = scenes[index];
+
+            const sceneButton = document.createElement('button');
+            sceneButton.type = 'button';
+            sceneButton.className = 'flex w-32 flex-shrink-0 flex-col gap-1 rounded-lg border border-slate-800/70 bg-slate-950/70 p-2 text-xs text-slate-300 transition hover:border-emerald-400/60';
+            sceneButton.title = `Cena ${index + 1}`;
+            sceneButton.addEventListener('click', () => seekToTime(scene.time));
+
+            const img = document.createElement('img');
+            img.src = `/video-previews/${videoPath}?scene=${index}`;
+            img.alt = `Cena ${index + 1} em ${formatTime(scene.time)}`;
+            img.loading = 'lazy';
+            img.className = 'w-full rounded-md object-cover';
+
+            const label = document.createElement('span');
+            label.className = 'text-center font-medium text-slate-200';
+            label.textContent = formatTime(scene.time);
+
+            sceneButton.appendChild(img);
+            sceneButton.appendChild(label);
+            scenesScroll.appendChild(sceneButton);
+        }
+
+        scenesBar.classList.remove('hidden');
+    }
+
+    // Named chapters read from the container (MKV/MP4)
+    function renderChapters() {
+        const chaptersList = document.getElementById('video-chapters');
+        const chapters = (previewInfo && previewInfo.chapters) || [];
+        if (!chaptersList || chaptersRendered || chapters.length === 0) return;
+        chaptersRendered = true;
+
+        const list = chaptersList.querySelector('.chapters-list');
+        chapters.forEach((chapter, index) => {
+            const chapterButton = document.createElement('button');
+            chapterButton.type = 'button';
+            chapterButton.className = 'chapter-item flex w-full items-center gap-3 rounded-lg border border-slate-800/70 bg-slate-950/70 p-2 text-left text-sm text-slate-300 transition hover:border-emerald-400/60';
+            chapterButton.dataset.start = chapter.start;
+            chapterButton.dataset.end = chapter.end;
+            chapterButton.addEventListener('click', () => seekToTime(chapter.start));
+
+            const img = document.createElement('img');
+            img.src = `/video-previews/${videoPath}?chapter=${index}`;
+            img.alt = chapter.title;
+            img.loading = 'lazy';
+            img.className = 'w-24 flex-shrink-0 rounded-md object-cover';
+            img.style.aspectRatio = '16 / 9';
+            img.onerror = () => img.remove();
+
+            const text = document.createElement('div');
+            text.className = 'min-w-0 flex-1';
+
+            const title = document.createElement('div');
+            title.className = 'truncate font-medium text-slate-200';
+            title.textContent = chapter.title;
+
+            const range = document.createElement('div');
+            range.className = 'text-xs text-slate-400';
+            range.textContent = `${formatTime(chapter.start)} – ${formatTime(chapter.end)}`;
+
+            text.appendChild(title);
+            text.appendChild(range);
+            chapterButton.appendChild(img);
+            chapterButton.appendChild(text);
+            list.appendChild(chapterButton);
+        });
+
+        chaptersList.classList.remove('hidden');
+        updateActiveChapter(video.currentTime);
+    }
+
+    function updateActiveChapter(currentTime) {
+        document.querySelectorAll('#video-chapters .chapter-item').forEach(item => {
+            const active = currentTime >= Number(item.dataset.start) && currentTime < Number(item.dataset.end);
+            item.classList.toggle('border-emerald-400/70', active);
+            item.classList.toggle('bg-emerald-500/15', active);
+        });
+    }
+
+    function createSpriteTile(thumbnail, sheet) {
+        const tile = thumbnail.sprite;
+        const column = tile.x / tile.width;
+        const row = tile.y / tile.height;
+        const position = (index, count) => count > 1 ? (index / (count - 1)) * 100 : 0;
+
+        const spriteDiv = document.createElement('div');
+        spriteDiv.setAttribute('role', 'img');
+        spriteDiv.setAttribute('aria-label', `Preview em ${formatTime(thumbnail.time)}`);
+        spriteDiv.className = 'w-full rounded-md bg-no-repeat shadow-inner shadow-black/30';
+        spriteDiv.style.aspectRatio = `${tile.width} / ${tile.height}`;
+        spriteDiv.style.backgroundImage = `url("/video-previews/${videoPath}?sprite=${tile.sheet}")`;
+        spriteDiv.style.backgroundSize = `${sheet.columns * 100}% ${sheet.rows * 100}%`;
+        spriteDiv.style.backgroundPosition = `${position(column, sheet.columns)}% ${position(row, sheet.rows)}%`;
+
+        return spriteDiv;
+    }
+
     function seekToTime(time) {
         video.currentTime = time;
         updateActiveThumbnail(time);
@@ -0,0 +1,113 @@
+// In/out markers for exporting a clip (/clip) or an animation (/animation), and frame snapshots (/frame)
+(function () {
+    const video = document.getElementById('main-video');
+    const tools = document.getElementById('video-tools');
+    if (!video || !tools) {
+        return;
+    }
+
+    const exportLink = tools.querySelector('[data-clip-export]');
+    const modeSelect = tools.querySelector('[data-clip-mode]');
+    const animationLink = tools.querySelector('[data-animation-export]');
+    const animationFormat = tools.querySelector('[data-animation-format]');
+    const animationWidth = tools.querySelector('[data-animation-width]');
+    const animationFps = tools.querySelector('[data-animation-fps]');
+    const markers = { in: null, out: null };
+
+    // Same limit as the server
+    const MAX_ANIMATION_SECONDS = 15;
+
+    function formatTime(seconds) {
+        const hours = Math.floor(seconds / 3600);
+        const minutes = Math.floor((seconds % 3600) / 60);
+        const secs = (seconds % 60).toFixed(1).padStart(4, '0');
+
+        if (hours > 0) {
+            return `${hours}:${minutes.toString().padStart(2, '0')}:${secs}`;
+        }
+        return `${minutes}:${secs}`;
+    }
+
+    function update() {
+        for (const name of ['in', 'out']) {
+            const label = tools.querySelector(`[data-clip-seek="${name}"]`);
+            label.textContent = markers[name] === null ? '--:--' : formatTime(markers[name]);
+        }
+
+        const start = markers.in === null ? 0 : markers.in;
+        const end = markers.out;
+        const valid = end !== null && end > start;
+
+        setEnabled(exportLink, valid);
+        exportLink.href = valid
+            ? `/clip/${videoPath}?start=${start.toFixed(3)}&end=${end.toFixed(3)}&mode=${modeSelect.value}`
+            : '#';
+
+        const animationValid = valid && end - start <= MAX_ANIMATION_SECONDS;
+        setEnabled(animationLink, animationValid);
+        animationLink.href = animationValid
+            ? `/animation/${videoPath}?start=${start.toFixed(3)}&end=${end.toFixed(3)}`
+                + `&format=${animationFormat.value}&w=${animationWidth.value}&fps=${animationFps.value}`
+            : '#';
+    }
+
+    function setEnabled(link, enabled) {
+        link.style.opacity = enabled ? '' : '0.5';
+        link.style.pointerEvents = enabled ? '' : 'none';
+        link.setAttribute('aria-disabled', enabled ? 'false' : 'true');
+    }
+
+    function mark(name) {
+        markers[name] = video.currentTime;
+        // Keep the range the right way round
+        if (markers.in !== null && markers.out !== null && markers.out < markers.in) {
+            [markers.in, markers.out] = [markers.out, markers.in];
+        }
+        update();
+    }
+
+    tools.querySelectorAll('[data-clip-action]').forEach(button => {
+        button.addEventListener('click', () => mark(button.dataset.clipAction));
+    });
+
+    tools.querySelectorAll('[data-clip-seek]').forEach(button => {
+        button.addEventListener('click', () => {
+            const time = markers[button.dataset.clipSeek];
+            if (time !== null) {
+                video.currentTime = time;
+            }
+        });
+    });
+
+    [modeSelect, animationFormat, animationWidth, animationFps].forEach(select => {
+        select.addEventListener('change', update);
+    });
+
+    const frameButton = tools.querySelector('[data-frame-save]');
+    if (frameButton) {
+        frameButton.addEventListener('click', () => {
+            video.pause();
+            const link = document.createElement('a');
+            link.href = `/frame/${videoPath}?t=${video.currentTime.toFixed(3)}&format=png`;
+            link.download = '';
+            document.body.appendChild(link);
+            link.click();
+            link.remove();
+        });
+    }
+
+    document.addEventListener('keydown', event => {
+        const target = event.target;
+        if (event.ctrlKey || event.metaKey || event.altKey || target.isContentEditable
+            || ['INPUT', 'TEXTAREA', 'SELECT'].includes(target.tagName)) {
+            return;
+        }
+        if (event.key === 'i' || event.key === 'I') {
+            mark('in');
+        } else if (event.key === 'o' || event.key === 'O') {
+            mark('out');
+        }
+    });
+
+    update();
+})();
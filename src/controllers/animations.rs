@@ -0,0 +1,108 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::header,
+    response::Response,
+};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio_util::io::ReaderStream;
+
+use crate::controllers::AppState;
+use crate::models::animations::{self, AnimationFormat, AnimationParams};
+use crate::models::{AppError, Result};
+
+#[derive(Debug, Deserialize)]
+pub struct AnimationQuery {
+    /// Segment in seconds
+    start: f64,
+    end: f64,
+    /// `gif` (default) or `webp`
+    #[serde(default)]
+    format: Option<String>,
+    #[serde(default)]
+    w: Option<u32>,
+    #[serde(default)]
+    fps: Option<u32>,
+}
+
+/// Serve or render an animated GIF/WebP of a video segment
+pub async fn animation(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Query(query): Query<AnimationQuery>,
+) -> Result<Response> {
+    if !state.config.ffmpeg_available {
+        return Err(AppError::NotFound(
+            "Animações requerem o ffmpeg".to_string(),
+        ));
+    }
+
+    let path = path.trim_matches('/');
+
+    let format = match query.format.as_deref() {
+        None => AnimationFormat::Gif,
+        Some(value) => AnimationFormat::parse(value).ok_or_else(|| {
+            AppError::BadRequest(format!("Formato de animação inválido: {}", value))
+        })?,
+    };
+
+    if !query.start.is_finite()
+        || !query.end.is_finite()
+        || query.start < 0.0
+        || query.end <= query.start
+    {
+        return Err(AppError::BadRequest(
+            "Intervalo de animação inválido".to_string(),
+        ));
+    }
+    if query.end - query.start > animations::MAX_SECONDS {
+        return Err(AppError::BadRequest(format!(
+            "Animações são limitadas a {} segundos",
+            animations::MAX_SECONDS
+        )));
+    }
+
+    let params = AnimationParams::new(query.start, query.end, query.w, query.fps, format);
+
+    let animation_path = animations::get_or_build(&state.config.base_dir_canonical, path, params)
+        .await?
+        .ok_or_else(|| {
+            AppError::BadRequest(format!(
+                "Nada para animar em {} entre {:.1}s e {:.1}s",
+                path, query.start, query.end
+            ))
+        })?;
+
+    let stem = std::path::Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("animation");
+    let filename = format!(
+        "{}_{:.1}-{:.1}.{}",
+        stem,
+        params.start,
+        params.end,
+        format.extension()
+    );
+    let encoded_filename = utf8_percent_encode(&filename, NON_ALPHANUMERIC).to_string();
+
+    let metadata = tokio::fs::metadata(&animation_path).await?;
+    let file = File::open(&animation_path).await?;
+    let body = Body::from_stream(ReaderStream::new(file));
+
+    let response = Response::builder()
+        .header(header::CONTENT_TYPE, format.mime_type())
+        .header(header::CONTENT_LENGTH, metadata.len().to_string())
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("inline; filename*=UTF-8''{}", encoded_filename),
+        )
+        .header(header::CACHE_CONTROL, "public, max-age=86400")
+        .body(body)
+        .unwrap();
+
+    Ok(response)
+}
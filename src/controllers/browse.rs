@@ -1,13 +1,18 @@
 use askama_axum::Template;
 use axum::{
     extract::{Path, Query, State},
+    http::HeaderMap,
     response::{IntoResponse, Redirect},
 };
 use serde::{Deserialize, Deserializer};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use crate::controllers::AppState;
-use crate::models::{fs, media, Result};
+use crate::models::placeholders::Placeholder;
+use crate::models::probe::MediaSummary;
+use crate::models::{fs, media, probe, progress, raw, thumbnails, Result};
 
 #[derive(Debug, Deserialize)]
 pub struct BrowseQuery {
@@ -15,6 +20,12 @@ pub struct BrowseQuery {
     view: String,
     #[serde(default, deserialize_with = "deserialize_bool_from_anything")]
     show_hidden: Option<bool>,
+    /// Column of the list view to sort by
+    #[serde(default)]
+    sort: Option<String>,
+    /// `asc` or `desc`, each column has its own default
+    #[serde(default)]
+    order: Option<String>,
 }
 
 fn default_view() -> String {
@@ -38,6 +49,44 @@ where
     }
 }
 
+/// Sortable columns of the list view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Name,
+    Size,
+    Modified,
+    Duration,
+    Resolution,
+}
+
+impl SortKey {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "name" => Some(Self::Name),
+            "size" => Some(Self::Size),
+            "modified" => Some(Self::Modified),
+            "duration" => Some(Self::Duration),
+            "resolution" => Some(Self::Resolution),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Size => "size",
+            Self::Modified => "modified",
+            Self::Duration => "duration",
+            Self::Resolution => "resolution",
+        }
+    }
+
+    /// Names read best A-Z, everything else biggest or newest first
+    fn descending_by_default(&self) -> bool {
+        !matches!(self, Self::Name)
+    }
+}
+
 #[derive(Template)]
 #[template(path = "browse.html")]
 struct BrowseTemplate {
@@ -47,6 +96,42 @@ struct BrowseTemplate {
     view_mode: String,
     show_hidden: bool,
     thumb_size: u32,
+    hover_previews: bool,
+    sort: SortKey,
+    descending: bool,
+    /// Unfinished audio and video of this browser, shown on the root page
+    continue_watching: Vec<ContinueItem>,
+}
+
+impl BrowseTemplate {
+    /// Link that sorts the list view by `column`, flipping the order when it is already active
+    fn sort_href(&self, column: &str) -> String {
+        let key = SortKey::parse(column).unwrap_or(SortKey::Name);
+        let descending = if key == self.sort {
+            !self.descending
+        } else {
+            key.descending_by_default()
+        };
+
+        format!(
+            "?view=list&sort={}&order={}{}",
+            key.as_str(),
+            if descending { "desc" } else { "asc" },
+            if self.show_hidden {
+                "&show_hidden=1"
+            } else {
+                ""
+            }
+        )
+    }
+
+    fn sort_arrow(&self, column: &str) -> &'static str {
+        match SortKey::parse(column) {
+            Some(key) if key == self.sort && self.descending => "▼",
+            Some(key) if key == self.sort => "▲",
+            _ => "",
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -57,18 +142,98 @@ struct ItemView {
     is_dir: bool,
     size: String,
     modified: String,
+    size_bytes: u64,
+    /// Modification time as Unix seconds
+    modified_ts: i64,
     icon: String,
     has_thumbnail: bool,
     media_kind: media::MediaKind,
+    placeholder: Option<Placeholder>,
+    /// RAW file hidden behind this JPEG when pairing is enabled
+    raw_pair: Option<RawPair>,
+    /// Duration and dimensions, when they were already probed
+    summary: Option<MediaSummary>,
+    /// Summary not cached yet, filled in by the browser after the page loads
+    summary_pending: bool,
+}
+
+#[derive(Debug)]
+struct ContinueItem {
+    name: String,
+    encoded_path: String,
+    percent: u32,
+    position: String,
+    duration: String,
+}
+
+impl From<progress::PlaybackProgress> for ContinueItem {
+    fn from(entry: progress::PlaybackProgress) -> Self {
+        Self {
+            name: entry
+                .path
+                .rsplit('/')
+                .next()
+                .unwrap_or(&entry.path)
+                .to_string(),
+            encoded_path: fs::url_encode_path(&entry.path),
+            percent: entry.percent(),
+            position: fs::format_duration(entry.position),
+            duration: fs::format_duration(entry.duration),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RawPair {
+    name: String,
+    encoded_path: String,
+}
+
+impl ItemView {
+    fn is_video(&self) -> bool {
+        self.media_kind == media::MediaKind::Video
+    }
+
+    fn has_summary(&self) -> bool {
+        !self.is_dir
+            && matches!(
+                self.media_kind,
+                media::MediaKind::Image | media::MediaKind::Video | media::MediaKind::Audio
+            )
+    }
+
+    fn duration(&self) -> String {
+        self.summary
+            .and_then(|s| s.formatted_duration())
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    fn resolution(&self) -> String {
+        self.summary
+            .and_then(|s| s.resolution())
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    /// Summary field shown over the grid thumbnail
+    fn badge_field(&self) -> &'static str {
+        match self.media_kind {
+            media::MediaKind::Image => "resolution",
+            _ => "duration",
+        }
+    }
+
+    fn badge(&self) -> Option<String> {
+        let summary = self.summary?;
+        match self.media_kind {
+            media::MediaKind::Image => summary.resolution(),
+            _ => summary.formatted_duration(),
+        }
+    }
 }
 
 impl From<fs::Item> for ItemView {
     fn from(item: fs::Item) -> Self {
-        let path = if item.is_dir {
-            item.rel_path.clone()
-        } else {
-            item.rel_path.clone()
-        };
+        let path = item.rel_path.clone();
 
         let (_, media_kind) = if !item.is_dir {
             let full_path = std::path::Path::new(&item.rel_path);
@@ -94,13 +259,108 @@ impl From<fs::Item> for ItemView {
                 fs::format_size(item.size)
             },
             modified: fs::format_datetime(&item.modified),
+            size_bytes: item.size,
+            modified_ts: item.modified.unix_timestamp(),
             icon,
             has_thumbnail: !item.is_dir && media_kind.has_thumbnail(),
             media_kind,
+            placeholder: None,
+            raw_pair: None,
+            summary: None,
+            summary_pending: false,
+        }
+    }
+}
+
+/// Sort items by a column; directories stay first and unknown values last
+fn sort_items(items: &mut [ItemView], key: SortKey, descending: bool) {
+    fn directed(ordering: Ordering, descending: bool) -> Ordering {
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    fn missing_last<T: PartialOrd>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                directed(a.partial_cmp(&b).unwrap_or(Ordering::Equal), descending)
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    let by_name = |a: &ItemView, b: &ItemView| a.name.to_lowercase().cmp(&b.name.to_lowercase());
+
+    items.sort_by(|a, b| {
+        b.is_dir.cmp(&a.is_dir).then_with(|| {
+            if a.is_dir {
+                return directed(by_name(a, b), key == SortKey::Name && descending);
+            }
+            match key {
+                SortKey::Name => directed(by_name(a, b), descending),
+                SortKey::Size => directed(a.size_bytes.cmp(&b.size_bytes), descending),
+                SortKey::Modified => directed(a.modified_ts.cmp(&b.modified_ts), descending),
+                SortKey::Duration => missing_last(
+                    a.summary.and_then(|s| s.duration),
+                    b.summary.and_then(|s| s.duration),
+                    descending,
+                ),
+                SortKey::Resolution => missing_last(
+                    a.summary.and_then(|s| s.pixels()),
+                    b.summary.and_then(|s| s.pixels()),
+                    descending,
+                ),
+            }
+            .then_with(|| by_name(a, b))
+        })
+    });
+}
+
+/// Hide RAW files that have a JPEG with the same name, flagging the JPEG instead
+fn pair_raw_files(items: &mut Vec<ItemView>) {
+    let stem = |name: &str| {
+        std::path::Path::new(name)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_lowercase())
+    };
+
+    let raws: HashMap<String, RawPair> = items
+        .iter()
+        .filter(|item| !item.is_dir && raw::is_raw(std::path::Path::new(&item.name)))
+        .filter_map(|item| {
+            let pair = RawPair {
+                name: item.name.clone(),
+                encoded_path: item.encoded_path.clone(),
+            };
+            Some((stem(&item.name)?, pair))
+        })
+        .collect();
+
+    if raws.is_empty() {
+        return;
+    }
+
+    let mut paired = HashSet::new();
+    for item in items
+        .iter_mut()
+        .filter(|item| !item.is_dir && raw::is_pair_target(std::path::Path::new(&item.name)))
+    {
+        if let Some(pair) = stem(&item.name).and_then(|s| raws.get(&s)) {
+            paired.insert(pair.name.clone());
+            item.raw_pair = Some(pair.clone());
         }
     }
+
+    items.retain(|item| item.is_dir || !paired.contains(&item.name));
 }
 
+/// How many unfinished files the root page lists
+const CONTINUE_WATCHING_LIMIT: usize = 12;
+
 /// Redirect root to /browse/
 pub async fn root_redirect() -> impl IntoResponse {
     Redirect::permanent("/browse/")
@@ -111,6 +371,7 @@ pub async fn browse(
     State(state): State<Arc<AppState>>,
     path: Option<Path<String>>,
     Query(query): Query<BrowseQuery>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse> {
     let path = path.map(|Path(p)| p).unwrap_or_default();
     let path = path.trim_matches('/');
@@ -121,7 +382,65 @@ pub async fn browse(
     let breadcrumbs = fs::breadcrumbs(path);
 
     // Convert items to view models
-    let items: Vec<ItemView> = items.into_iter().map(ItemView::from).collect();
+    let mut items: Vec<ItemView> = items.into_iter().map(ItemView::from).collect();
+
+    if state.config.pair_raw {
+        pair_raw_files(&mut items);
+    }
+
+    // Only cached summaries are read here; the rest are probed on request from the page
+    for item in items.iter_mut().filter(|item| item.has_summary()) {
+        item.summary = probe::cached_summary(
+            &state.config.base_dir_canonical,
+            &item.rel_path,
+            item.size_bytes,
+            item.modified_ts.max(0) as u64,
+            item.media_kind,
+        );
+        item.summary_pending = item.summary.is_none()
+            && (item.media_kind == media::MediaKind::Image || state.config.ffmpeg_available);
+    }
+
+    let sort = query
+        .sort
+        .as_deref()
+        .and_then(SortKey::parse)
+        .unwrap_or(SortKey::Name);
+    let descending = match query.order.as_deref() {
+        Some("asc") => false,
+        Some("desc") => true,
+        _ => sort.descending_by_default(),
+    };
+    if sort != SortKey::Name || descending {
+        sort_items(&mut items, sort, descending);
+    }
+
+    // Placeholders of thumbnails that were already generated, painted while they load
+    if query.view == "grid" {
+        for item in items
+            .iter_mut()
+            .filter(|item| item.is_dir || item.has_thumbnail)
+        {
+            item.placeholder = thumbnails::cached_placeholder(
+                &state.config.base_dir_canonical,
+                &item.rel_path,
+                state.config.thumb_size,
+            );
+        }
+    }
+
+    let continue_watching = match crate::controllers::progress::client_id(&headers) {
+        Some(client_id) if path.is_empty() => progress::recent(
+            &state.config.base_dir_canonical,
+            &client_id,
+            CONTINUE_WATCHING_LIMIT,
+        )
+        .await
+        .into_iter()
+        .map(ContinueItem::from)
+        .collect(),
+        _ => Vec::new(),
+    };
 
     let template = BrowseTemplate {
         items,
@@ -130,6 +449,10 @@ pub async fn browse(
         view_mode: query.view,
         show_hidden,
         thumb_size: state.config.thumb_size,
+        hover_previews: state.config.hover_previews,
+        sort,
+        descending,
+        continue_watching,
     };
 
     Ok(template)
@@ -0,0 +1,90 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::header,
+    response::Response,
+};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::controllers::AppState;
+use crate::models::clips::{self, ClipMode};
+use crate::models::{AppError, Result};
+
+#[derive(Debug, Deserialize)]
+pub struct ClipQuery {
+    /// Range in seconds
+    start: f64,
+    end: f64,
+    /// `copy` (default) or `reencode`
+    #[serde(default)]
+    mode: Option<String>,
+}
+
+/// Cut a time range out of a video and stream it as a download
+pub async fn clip(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Query(query): Query<ClipQuery>,
+) -> Result<Response> {
+    if !state.config.ffmpeg_available {
+        return Err(AppError::NotFound(
+            "Exportar trechos requer o ffmpeg".to_string(),
+        ));
+    }
+
+    let path = path.trim_matches('/');
+
+    let mode = match query.mode.as_deref() {
+        None => ClipMode::Copy,
+        Some(value) => ClipMode::parse(value)
+            .ok_or_else(|| AppError::BadRequest(format!("Modo de recorte inválido: {}", value)))?,
+    };
+
+    if !query.start.is_finite()
+        || !query.end.is_finite()
+        || query.start < 0.0
+        || query.end <= query.start
+    {
+        return Err(AppError::BadRequest(
+            "Intervalo de recorte inválido".to_string(),
+        ));
+    }
+    if mode == ClipMode::Reencode && query.end - query.start > clips::MAX_REENCODE_SECONDS {
+        return Err(AppError::BadRequest(format!(
+            "Trechos recodificados são limitados a {} minutos",
+            clips::MAX_REENCODE_SECONDS / 60.0
+        )));
+    }
+
+    let clip = clips::start_clip(
+        &state.config.base_dir_canonical,
+        path,
+        query.start,
+        query.end,
+        mode,
+    )
+    .await?
+    .ok_or_else(|| {
+        AppError::BadRequest(format!(
+            "Nada para recortar em {} entre {:.1}s e {:.1}s",
+            path, query.start, query.end
+        ))
+    })?;
+
+    let encoded_filename = utf8_percent_encode(&clip.filename, NON_ALPHANUMERIC).to_string();
+
+    // The length is unknown until ffmpeg is done, so the body is chunked
+    let response = Response::builder()
+        .header(header::CONTENT_TYPE, clip.content_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename*=UTF-8''{}", encoded_filename),
+        )
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from_stream(clip.body))
+        .unwrap();
+
+    Ok(response)
+}
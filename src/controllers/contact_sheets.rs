@@ -0,0 +1,71 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::header,
+    response::Response,
+};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio_util::io::ReaderStream;
+
+use crate::controllers::AppState;
+use crate::models::contact_sheets::{self, SheetParams};
+use crate::models::{AppError, Result};
+
+#[derive(Debug, Deserialize)]
+pub struct ContactSheetQuery {
+    /// Grid size, clamped to 1..=10 each
+    #[serde(default)]
+    cols: Option<u32>,
+    #[serde(default)]
+    rows: Option<u32>,
+    /// Sheet width in pixels
+    #[serde(default)]
+    w: Option<u32>,
+}
+
+/// Serve or build the contact sheet (frame mosaic) of a video
+pub async fn contact_sheet(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Query(query): Query<ContactSheetQuery>,
+) -> Result<Response> {
+    if !state.config.ffmpeg_available {
+        return Err(AppError::NotFound(
+            "Folhas de contato requerem o ffmpeg".to_string(),
+        ));
+    }
+
+    let path = path.trim_matches('/');
+    let params = SheetParams::new(query.cols, query.rows, query.w);
+
+    let sheet_path = contact_sheets::get_or_build(&state.config.base_dir_canonical, path, params)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Nenhuma folha de contato para {}", path)))?;
+
+    let stem = std::path::Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("video");
+    let filename = format!("{}_{}x{}.jpg", stem, params.columns, params.rows);
+    let encoded_filename = utf8_percent_encode(&filename, NON_ALPHANUMERIC).to_string();
+
+    let metadata = tokio::fs::metadata(&sheet_path).await?;
+    let file = File::open(&sheet_path).await?;
+    let body = Body::from_stream(ReaderStream::new(file));
+
+    let response = Response::builder()
+        .header(header::CONTENT_TYPE, "image/jpeg")
+        .header(header::CONTENT_LENGTH, metadata.len().to_string())
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("inline; filename*=UTF-8''{}", encoded_filename),
+        )
+        .header(header::CACHE_CONTROL, "public, max-age=86400")
+        .body(body)
+        .unwrap();
+
+    Ok(response)
+}
@@ -1,12 +1,13 @@
 use askama_axum::Template;
 use axum::{
     extract::{Path, State},
+    http::HeaderMap,
     response::IntoResponse,
 };
 use std::sync::Arc;
 
 use crate::controllers::AppState;
-use crate::models::{fs, media, Result};
+use crate::models::{fs, media, probe, progress, raw, tags, Result};
 
 #[derive(Template)]
 #[template(path = "file.html")]
@@ -18,21 +19,76 @@ struct FileTemplate {
     formatted_size: String,
     formatted_modified: String,
     encoded_path: String,
+    /// Camera RAW files are viewed through their converted embedded preview
+    is_raw: bool,
+    /// Codec and stream details of audio and video files
+    media_metadata: Option<probe::MediaMetadata>,
+    /// Title, artist, album and other tags of audio files
+    audio_tags: Option<tags::AudioTags>,
+    /// Version the tag edit form sends back, when tags of this file can be edited
+    tag_version: Option<String>,
+    /// Current values of the tag edit form
+    edit_tags: tags::AudioTags,
+    /// Where this browser stopped playing the file last time
+    resume_position: Option<f64>,
+    /// Whether the ffmpeg-backed video tools (clip export, frame snapshot) are shown
+    video_tools: bool,
 }
 
 /// Show file page
 pub async fn file_page(
     State(state): State<Arc<AppState>>,
     Path(path): Path<String>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse> {
     let path = path.trim_matches('/');
-    
+
     let file_info = fs::file_info(&state.config.base_dir_canonical, path)?;
     let breadcrumbs = fs::breadcrumbs(path);
-    
+
     let full_path = fs::canonicalize_in_base(&state.config.base_dir_canonical, path)?;
     let (mime_type, media_kind) = media::detect(&full_path);
-    
+
+    let is_raw = raw::is_raw(&full_path);
+
+    // A failed probe only hides the panel
+    let media_metadata = probe::get_or_probe(
+        &state.config.base_dir_canonical,
+        path,
+        state.config.ffmpeg_available,
+    )
+    .await
+    .unwrap_or_else(|e| {
+        tracing::warn!("Failed to probe {}: {}", path, e);
+        None
+    });
+
+    // Unreadable tags only hide the panel
+    let edit_tags = tags::get(&state.config.base_dir_canonical, path)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to read tags of {}: {}", path, e);
+            None
+        })
+        .unwrap_or_default();
+    let audio_tags = Some(edit_tags.clone()).filter(|tags| !tags.is_empty());
+
+    let tag_version = if state.config.tag_editing && tags::is_editable(&full_path) {
+        tokio::fs::metadata(&full_path)
+            .await
+            .ok()
+            .map(|metadata| tags::version(&metadata))
+    } else {
+        None
+    };
+
+    let resume_position = match crate::controllers::progress::client_id(&headers) {
+        Some(client_id) => progress::get(&state.config.base_dir_canonical, &client_id, path)
+            .await
+            .map(|entry| entry.position),
+        None => None,
+    };
+
     let template = FileTemplate {
         formatted_size: fs::format_size(file_info.size),
         formatted_modified: fs::format_datetime(&file_info.modified),
@@ -41,7 +97,14 @@ pub async fn file_page(
         breadcrumbs,
         mime_type,
         media_kind,
+        is_raw,
+        media_metadata,
+        audio_tags,
+        tag_version,
+        edit_tags,
+        resume_position,
+        video_tools: state.config.ffmpeg_available,
     };
-    
+
     Ok(template)
-}
\ No newline at end of file
+}
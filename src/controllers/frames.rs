@@ -0,0 +1,75 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::Response,
+};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::controllers::AppState;
+use crate::models::frames::{self, FrameFormat};
+use crate::models::{AppError, Result};
+
+#[derive(Debug, Deserialize)]
+pub struct FrameQuery {
+    /// Timestamp in seconds
+    t: f64,
+    /// `png` (default) or `jpeg`
+    #[serde(default)]
+    format: Option<String>,
+    /// Optional maximum width; the frame is never upscaled
+    #[serde(default)]
+    w: Option<u32>,
+}
+
+/// Extract a single frame of a video at an exact timestamp
+pub async fn frame(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Query(query): Query<FrameQuery>,
+) -> Result<Response> {
+    if !state.config.ffmpeg_available {
+        return Err(AppError::NotFound(
+            "Extrair quadros requer o ffmpeg".to_string(),
+        ));
+    }
+
+    let path = path.trim_matches('/');
+
+    let format = match query.format.as_deref() {
+        None => FrameFormat::Png,
+        Some(value) => FrameFormat::parse(value).ok_or_else(|| {
+            AppError::BadRequest(format!("Formato de quadro inválido: {}", value))
+        })?,
+    };
+
+    if !query.t.is_finite() || query.t < 0.0 {
+        return Err(AppError::BadRequest("Instante inválido".to_string()));
+    }
+
+    let frame = frames::extract_frame(
+        &state.config.base_dir_canonical,
+        path,
+        query.t,
+        format,
+        query.w.filter(|w| *w > 0),
+    )
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Nenhum quadro em {} em {:.3}s", path, query.t)))?;
+
+    let encoded_filename = utf8_percent_encode(&frame.filename, NON_ALPHANUMERIC).to_string();
+
+    let response = Response::builder()
+        .header(header::CONTENT_TYPE, format.mime_type())
+        .header(header::CONTENT_LENGTH, frame.data.len().to_string())
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("inline; filename*=UTF-8''{}", encoded_filename),
+        )
+        .header(header::CACHE_CONTROL, "public, max-age=86400")
+        .body(frame.data.into())
+        .unwrap();
+
+    Ok(response)
+}
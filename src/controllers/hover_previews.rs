@@ -0,0 +1,58 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::Response,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio_util::io::ReaderStream;
+
+use crate::controllers::AppState;
+use crate::models::{hover_previews, AppError, Result};
+
+#[derive(Debug, Deserialize)]
+pub struct HoverQuery {
+    #[serde(default)]
+    w: Option<u32>,
+}
+
+/// Serve or generate the animated hover preview of a video
+pub async fn hover_preview(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Query(query): Query<HoverQuery>,
+) -> Result<Response> {
+    if !state.config.hover_previews {
+        return Err(AppError::NotFound(
+            "Prévias ao passar o mouse desativadas".to_string(),
+        ));
+    }
+
+    let path = path.trim_matches('/');
+    let width = query.w.unwrap_or(state.config.thumb_size);
+
+    let preview_path = hover_previews::get_or_build(
+        &state.config.base_dir_canonical,
+        path,
+        width,
+        state.config.ffmpeg_available,
+    )
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Nenhuma prévia animada para: {}", path)))?;
+
+    let metadata = tokio::fs::metadata(&preview_path).await?;
+    let file = File::open(&preview_path).await?;
+    let body = Body::from_stream(ReaderStream::new(file));
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "video/mp4")
+        .header(header::CONTENT_LENGTH, metadata.len().to_string())
+        .header(header::CACHE_CONTROL, "public, max-age=86400")
+        .body(body)
+        .unwrap();
+
+    Ok(response)
+}
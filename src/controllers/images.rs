@@ -0,0 +1,69 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::Response,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio_util::io::ReaderStream;
+
+use crate::controllers::AppState;
+use crate::models::image_transform::{self, Fit, OutputFormat, TransformParams};
+use crate::models::{AppError, Result};
+
+#[derive(Debug, Deserialize)]
+pub struct ImageQuery {
+    #[serde(default)]
+    w: Option<u32>,
+    #[serde(default)]
+    h: Option<u32>,
+    #[serde(default)]
+    fit: Option<String>,
+    #[serde(default)]
+    format: Option<String>,
+    #[serde(default)]
+    q: Option<u8>,
+}
+
+/// Serve a resized/cropped/re-encoded copy of an image
+pub async fn image(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Query(query): Query<ImageQuery>,
+) -> Result<Response> {
+    let path = path.trim_matches('/');
+
+    let fit = match query.fit.as_deref() {
+        Some(value) => Fit::parse(value)
+            .ok_or_else(|| AppError::BadRequest(format!("Valor inválido para 'fit': {}", value)))?,
+        None => Fit::Contain,
+    };
+    let format = match query.format.as_deref() {
+        Some(value) => OutputFormat::parse(value).ok_or_else(|| {
+            AppError::BadRequest(format!("Valor inválido para 'format': {}", value))
+        })?,
+        None => OutputFormat::Jpeg,
+    };
+
+    let params = TransformParams::new(query.w, query.h, fit, format, query.q);
+
+    let image_path = image_transform::get_or_build(&state.config.base_dir_canonical, path, params)
+        .await?
+        .ok_or_else(|| AppError::BadRequest(format!("Não é uma imagem: {}", path)))?;
+
+    let metadata = tokio::fs::metadata(&image_path).await?;
+    let file = File::open(&image_path).await?;
+    let body = Body::from_stream(ReaderStream::new(file));
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, format.mime_type())
+        .header(header::CONTENT_LENGTH, metadata.len().to_string())
+        .header(header::CACHE_CONTROL, "public, max-age=86400")
+        .body(body)
+        .unwrap();
+
+    Ok(response)
+}
@@ -0,0 +1,41 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::controllers::AppState;
+use crate::models::{probe, AppError, Result};
+
+#[derive(Debug, Deserialize)]
+pub struct MetaQuery {
+    /// Only return the duration and dimensions shown in listings (images included)
+    #[serde(default)]
+    summary: Option<String>,
+}
+
+/// Codec, container and stream details of an audio or video file as JSON
+pub async fn meta(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Query(query): Query<MetaQuery>,
+) -> Result<Response> {
+    let path = path.trim_matches('/');
+    let base_dir = &state.config.base_dir_canonical;
+    let ffmpeg_available = state.config.ffmpeg_available;
+
+    if query.summary.is_some() {
+        let summary = probe::get_or_build_summary(base_dir, path, ffmpeg_available)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Nenhum resumo de mídia para: {}", path)))?;
+        return Ok(Json(summary).into_response());
+    }
+
+    let metadata = probe::get_or_probe(base_dir, path, ffmpeg_available)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Nenhum metadado de mídia para: {}", path)))?;
+
+    Ok(Json(metadata).into_response())
+}
@@ -1,12 +1,23 @@
+pub mod animations;
 pub mod browse;
+pub mod clips;
+pub mod contact_sheets;
 pub mod content;
 pub mod dev;
 pub mod download;
 pub mod file;
+pub mod frames;
+pub mod hover_previews;
+pub mod images;
+pub mod meta;
+pub mod music;
+pub mod progress;
 pub mod search;
+pub mod tags;
 pub mod thumbs;
 pub mod upload;
 pub mod video_previews;
+pub mod waveforms;
 
 use std::sync::Arc;
 
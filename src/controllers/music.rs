@@ -0,0 +1,203 @@
+use askama_axum::Template;
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::controllers::AppState;
+use crate::models::music::{self, MusicAlbum, MusicArtist, MusicGenre, MusicLibrary};
+use crate::models::{fs, AppError, Result};
+
+/// Albums per page of the all albums grid
+const ALBUMS_PER_PAGE: usize = 120;
+
+#[derive(Template)]
+#[template(path = "music_indexing.html")]
+struct MusicIndexingTemplate {
+    breadcrumbs: Vec<fs::Breadcrumb>,
+    scanned: usize,
+}
+
+#[derive(Template)]
+#[template(path = "music_artists.html")]
+struct MusicArtistsTemplate<'a> {
+    breadcrumbs: Vec<fs::Breadcrumb>,
+    section: &'static str,
+    library: &'a MusicLibrary,
+    artists: &'a [MusicArtist],
+    genres: &'a [MusicGenre],
+}
+
+#[derive(Template)]
+#[template(path = "music_albums.html")]
+struct MusicAlbumsTemplate<'a> {
+    breadcrumbs: Vec<fs::Breadcrumb>,
+    section: &'static str,
+    library: &'a MusicLibrary,
+    heading: String,
+    albums: Vec<&'a MusicAlbum>,
+    thumb_size: u32,
+    page: usize,
+    page_count: usize,
+}
+
+#[derive(Template)]
+#[template(path = "music_album.html")]
+struct MusicAlbumTemplate<'a> {
+    breadcrumbs: Vec<fs::Breadcrumb>,
+    section: &'static str,
+    library: &'a MusicLibrary,
+    album: &'a MusicAlbum,
+    thumb_size: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlbumsQuery {
+    #[serde(default)]
+    page: Option<usize>,
+}
+
+/// The index, starting its build if needed
+fn current(state: &AppState) -> Option<Arc<MusicLibrary>> {
+    music::current(&state.config.base_dir_canonical, state.config.show_hidden)
+}
+
+/// Progress page shown while the first build runs; it reloads itself
+fn indexing() -> Response {
+    MusicIndexingTemplate {
+        breadcrumbs: fs::breadcrumbs(""),
+        scanned: music::scanned(),
+    }
+    .into_response()
+}
+
+/// Artists of the library
+pub async fn artists(State(state): State<Arc<AppState>>) -> Result<Response> {
+    let Some(library) = current(&state) else {
+        return Ok(indexing());
+    };
+
+    Ok(MusicArtistsTemplate {
+        breadcrumbs: fs::breadcrumbs(""),
+        section: "artists",
+        library: &library,
+        artists: &library.artists,
+        genres: &[],
+    }
+    .into_response())
+}
+
+/// Genres of the library
+pub async fn genres(State(state): State<Arc<AppState>>) -> Result<Response> {
+    let Some(library) = current(&state) else {
+        return Ok(indexing());
+    };
+
+    Ok(MusicArtistsTemplate {
+        breadcrumbs: fs::breadcrumbs(""),
+        section: "genres",
+        library: &library,
+        artists: &[],
+        genres: &library.genres,
+    }
+    .into_response())
+}
+
+/// Every album, paginated
+pub async fn albums(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AlbumsQuery>,
+) -> Result<Response> {
+    let Some(library) = current(&state) else {
+        return Ok(indexing());
+    };
+
+    let page_count = library.albums.len().div_ceil(ALBUMS_PER_PAGE).max(1);
+    let page = query.page.unwrap_or(1).clamp(1, page_count);
+    let albums = library
+        .albums
+        .iter()
+        .skip((page - 1) * ALBUMS_PER_PAGE)
+        .take(ALBUMS_PER_PAGE)
+        .collect();
+
+    Ok(MusicAlbumsTemplate {
+        breadcrumbs: fs::breadcrumbs(""),
+        section: "albums",
+        library: &library,
+        heading: "Álbuns".to_string(),
+        albums,
+        thumb_size: state.config.thumb_size,
+        page,
+        page_count,
+    }
+    .into_response())
+}
+
+/// Albums of one artist, oldest first
+pub async fn artist(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Response> {
+    let Some(library) = current(&state) else {
+        return Ok(indexing());
+    };
+    let artist = library
+        .artist(&id)
+        .ok_or_else(|| AppError::NotFound(format!("Artista não encontrado: {}", id)))?;
+
+    Ok(MusicAlbumsTemplate {
+        breadcrumbs: fs::breadcrumbs(""),
+        section: "artists",
+        library: &library,
+        heading: artist.name.clone(),
+        albums: library.albums_at(&artist.albums),
+        thumb_size: state.config.thumb_size,
+        page: 1,
+        page_count: 1,
+    }
+    .into_response())
+}
+
+/// Albums of one genre
+pub async fn genre(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Response> {
+    let Some(library) = current(&state) else {
+        return Ok(indexing());
+    };
+    let genre = library
+        .genre(&id)
+        .ok_or_else(|| AppError::NotFound(format!("Gênero não encontrado: {}", id)))?;
+
+    Ok(MusicAlbumsTemplate {
+        breadcrumbs: fs::breadcrumbs(""),
+        section: "genres",
+        library: &library,
+        heading: genre.name.clone(),
+        albums: library.albums_at(&genre.albums),
+        thumb_size: state.config.thumb_size,
+        page: 1,
+        page_count: 1,
+    }
+    .into_response())
+}
+
+/// Tracks of an album in disc/track order, with a player that queues them
+pub async fn album(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Response> {
+    let Some(library) = current(&state) else {
+        return Ok(indexing());
+    };
+    let album = library
+        .album(&id)
+        .ok_or_else(|| AppError::NotFound(format!("Álbum não encontrado: {}", id)))?;
+
+    Ok(MusicAlbumTemplate {
+        breadcrumbs: fs::breadcrumbs(""),
+        section: "albums",
+        library: &library,
+        album,
+        thumb_size: state.config.thumb_size,
+    }
+    .into_response())
+}
@@ -0,0 +1,89 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::controllers::AppState;
+use crate::models::{progress, AppError, Result};
+
+/// Cookie holding the anonymous client ID
+const CLIENT_COOKIE: &str = "media_serve_client";
+
+#[derive(Debug, Deserialize)]
+pub struct ProgressBody {
+    /// Current position in seconds
+    position: f64,
+    /// Duration reported by the player in seconds
+    duration: f64,
+}
+
+/// Client ID from the request cookies, if it is one we issued
+pub fn client_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == CLIENT_COOKIE)
+        .map(|(_, value)| value.to_string())
+        .filter(|value| progress::is_valid_client_id(value))
+}
+
+/// Store the playback position reported by a player
+pub async fn save_progress(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<ProgressBody>,
+) -> Result<Response> {
+    let path = path.trim_matches('/');
+
+    if !body.position.is_finite() || !body.duration.is_finite() || body.position < 0.0 {
+        return Err(AppError::BadRequest(
+            "Posição de reprodução inválida".to_string(),
+        ));
+    }
+
+    // First report from this browser, hand out an ID
+    let (client_id, new_cookie) = match client_id(&headers) {
+        Some(id) => (id, None),
+        None => {
+            let id = progress::new_client_id();
+            let cookie = format!(
+                "{}={}; Path=/; Max-Age={}; SameSite=Lax; HttpOnly",
+                CLIENT_COOKIE,
+                id,
+                progress::CLIENT_MAX_AGE.as_secs()
+            );
+            (id, Some(cookie))
+        }
+    };
+
+    let recorded = progress::record(
+        &state.config.base_dir_canonical,
+        &client_id,
+        path,
+        body.position,
+        body.duration,
+    )
+    .await?;
+
+    if !recorded {
+        return Err(AppError::NotFound(format!(
+            "Não é um arquivo reproduzível: {}",
+            path
+        )));
+    }
+
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    if let Some(cookie) = new_cookie.and_then(|c| c.parse().ok()) {
+        response.headers_mut().insert(header::SET_COOKIE, cookie);
+    }
+
+    Ok(response)
+}
@@ -5,11 +5,12 @@ use axum::{
 };
 use serde::Deserialize;
 use serde::Serialize;
-use std::path::PathBuf;
+use std::path::Path;
 use std::sync::Arc;
 
 use crate::controllers::AppState;
-use crate::models::{fs, AppError, Result};
+use crate::models::placeholders::Placeholder;
+use crate::models::{fs, tags, thumbnails, AppError, Result};
 
 #[derive(Debug, Deserialize)]
 pub struct SearchQuery {
@@ -28,6 +29,10 @@ pub struct SearchResultItem {
     pub is_dir: bool,
     pub size: String,
     pub modified: String,
+    /// Placeholder of the item's thumbnail, if one was generated already
+    pub placeholder: Option<Placeholder>,
+    /// Tags of audio files
+    pub tags: Option<tags::AudioTags>,
 }
 
 pub async fn search(
@@ -43,23 +48,26 @@ pub async fn search(
     let show_hidden = query.show_hidden.unwrap_or(state.config.show_hidden);
     let base = state.config.base_dir_canonical.clone();
     let needle = q.to_lowercase();
+    let thumb_size = state.config.thumb_size;
 
-    let results =
-        tokio::task::spawn_blocking(move || search_fs(&base, &needle, show_hidden, limit))
-            .await
-            .map_err(|e| AppError::Internal(format!("join error: {}", e)))??;
+    let results = tokio::task::spawn_blocking(move || {
+        search_fs(&base, &needle, show_hidden, limit, thumb_size)
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("join error: {}", e)))??;
 
     Ok(Json(results))
 }
 
 fn search_fs(
-    base: &PathBuf,
+    base: &Path,
     needle: &str,
     show_hidden: bool,
     limit: usize,
+    thumb_size: u32,
 ) -> Result<Vec<SearchResultItem>> {
     let mut out = Vec::with_capacity(limit.min(256));
-    let mut stack = vec![base.clone()];
+    let mut stack = vec![base.to_path_buf()];
 
     while let Some(dir) = stack.pop() {
         for entry_res in std::fs::read_dir(&dir)? {
@@ -99,6 +107,8 @@ fn search_fs(
                         modified: fs::format_datetime(&time::OffsetDateTime::from(
                             meta.modified()?,
                         )),
+                        placeholder: thumbnails::cached_placeholder(base, &rel, thumb_size),
+                        tags: None,
                     });
                     if out.len() >= limit {
                         return Ok(out);
@@ -113,25 +123,35 @@ fn search_fs(
                 continue;
             }
 
-            // Files
-            if name.to_lowercase().contains(needle) {
-                let meta = match entry.metadata() {
-                    Ok(m) => m,
-                    Err(_) => continue,
-                };
-                let abs = entry.path();
-                let rel = fs::relative_from_base(base, &abs)?;
-                out.push(SearchResultItem {
-                    name: name.clone(),
-                    rel_path: rel.clone(),
-                    encoded_path: fs::url_encode_path(&rel),
-                    is_dir: false,
-                    size: fs::format_size(meta.len()),
-                    modified: fs::format_datetime(&time::OffsetDateTime::from(meta.modified()?)),
-                });
-                if out.len() >= limit {
-                    return Ok(out);
-                }
+            // Files; audio also matches on its title, artist, album and genre tags
+            let name_match = name.to_lowercase().contains(needle);
+            let abs = entry.path();
+            if !name_match && !tags::is_audio_path(&abs) {
+                continue;
+            }
+
+            let meta = match entry.metadata() {
+                Ok(m) => m,
+                Err(_) => continue,
+            };
+            let rel = fs::relative_from_base(base, &abs)?;
+            let audio_tags = tags::get_or_read(base, &rel, &abs, &meta);
+            if !name_match && !audio_tags.as_ref().is_some_and(|t| t.matches(needle)) {
+                continue;
+            }
+
+            out.push(SearchResultItem {
+                name: name.clone(),
+                rel_path: rel.clone(),
+                encoded_path: fs::url_encode_path(&rel),
+                is_dir: false,
+                size: fs::format_size(meta.len()),
+                modified: fs::format_datetime(&time::OffsetDateTime::from(meta.modified()?)),
+                placeholder: thumbnails::cached_placeholder(base, &rel, thumb_size),
+                tags: audio_tags,
+            });
+            if out.len() >= limit {
+                return Ok(out);
             }
         }
     }
@@ -154,7 +174,6 @@ pub struct SearchPageQuery {
 
 /// Serves the search page (HTML). The actual results are fetched via /search JSON endpoint.
 pub async fn search_page(
-    State(state): State<Arc<AppState>>,
     Query(query): Query<SearchPageQuery>,
 ) -> Result<impl axum::response::IntoResponse> {
     let crumbs = crate::models::fs::breadcrumbs("");
@@ -0,0 +1,135 @@
+use axum::{
+    extract::{Multipart, Path, State},
+    response::{IntoResponse, Redirect},
+    Json,
+};
+use std::sync::Arc;
+
+use crate::controllers::AppState;
+use crate::models::tags::{self, AudioTags, TagWrite};
+use crate::models::{fs, AppError, Result};
+
+/// Largest tag edit form accepted, cover included
+pub const MAX_EDIT_BYTES: usize = 16 * 1024 * 1024;
+
+/// Latest year the edit form accepts
+const MAX_YEAR: u32 = 9999;
+
+/// Title, artist, album, track, year, genre and duration of an audio file as JSON
+pub async fn tags(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<Json<AudioTags>> {
+    let path = path.trim_matches('/');
+
+    let tags = tags::get(&state.config.base_dir_canonical, path)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Nenhuma tag de áudio para: {}", path)))?;
+
+    Ok(Json(tags))
+}
+
+/// Save the tag edit form of the file page, then go back to it
+pub async fn update_tags(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse> {
+    let path = path.trim_matches('/');
+
+    if !state.config.tag_editing {
+        return Err(AppError::Forbidden(
+            "edição de tags desativada (inicie com --tag-editing)".to_string(),
+        ));
+    }
+
+    let mut version = None;
+    let mut edit = AudioTags::default();
+    let mut cover = None;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("formulário inválido: {}", e)))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+        let bytes = field
+            .bytes()
+            .await
+            .map_err(|e| AppError::BadRequest(format!("formulário inválido: {}", e)))?;
+
+        if name == "cover" {
+            // An empty file input means the cover stays as it is
+            if !bytes.is_empty() {
+                cover = Some(bytes.to_vec());
+            }
+            continue;
+        }
+
+        let value = String::from_utf8_lossy(&bytes).trim().to_string();
+        let text = (!value.is_empty()).then(|| value.clone());
+        match name.as_str() {
+            "version" => version = Some(value),
+            "title" => edit.title = text,
+            "artist" => edit.artist = text,
+            "album" => edit.album = text,
+            "album_artist" => edit.album_artist = text,
+            "genre" => edit.genre = text,
+            "track" => edit.track = parse_number(&name, text)?,
+            "track_total" => edit.track_total = parse_number(&name, text)?,
+            "disc" => edit.disc = parse_number(&name, text)?,
+            "disc_total" => edit.disc_total = parse_number(&name, text)?,
+            "year" => {
+                edit.year = parse_number(&name, text)?;
+                if edit.year.is_some_and(|year| year > MAX_YEAR) {
+                    return Err(AppError::BadRequest(format!("ano inválido: {}", value)));
+                }
+            }
+            _ => tracing::debug!("Skipping unknown tag field: {}", name),
+        }
+    }
+
+    let version =
+        version.ok_or_else(|| AppError::BadRequest("campo 'version' é obrigatório".to_string()))?;
+
+    match tags::write(
+        &state.config.base_dir_canonical,
+        path,
+        &version,
+        edit,
+        cover,
+    )
+    .await?
+    {
+        TagWrite::Written => Ok(Redirect::to(&format!(
+            "/file/{}",
+            fs::url_encode_path(path)
+        ))),
+        TagWrite::Changed => Err(AppError::Conflict(
+            "O arquivo foi alterado depois que o formulário foi aberto; nada foi salvo."
+                .to_string(),
+        )),
+        TagWrite::Unsupported => Err(AppError::BadRequest(format!(
+            "formato sem suporte a edição de tags: {}",
+            path
+        ))),
+        TagWrite::InvalidCover => Err(AppError::BadRequest(
+            "a capa deve ser uma imagem JPEG, PNG, GIF, BMP ou TIFF".to_string(),
+        )),
+    }
+}
+
+/// Positive number of a numeric field; empty removes the tag
+fn parse_number(name: &str, value: Option<String>) -> Result<Option<u32>> {
+    match value {
+        None => Ok(None),
+        Some(value) => match value.parse::<u32>() {
+            Ok(0) => Ok(None),
+            Ok(number) => Ok(Some(number)),
+            Err(_) => Err(AppError::BadRequest(format!(
+                "valor inválido para '{}': {}",
+                name, value
+            ))),
+        },
+    }
+}
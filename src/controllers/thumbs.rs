@@ -1,7 +1,7 @@
 use axum::{
     body::Body,
     extract::{Path, Query, State},
-    http::{header, StatusCode},
+    http::{header, HeaderMap, StatusCode},
     response::Response,
 };
 use serde::Deserialize;
@@ -10,7 +10,7 @@ use tokio::fs::File;
 use tokio_util::io::ReaderStream;
 
 use crate::controllers::AppState;
-use crate::models::{thumbnails, Result};
+use crate::models::{fs, thumbnails, workers::Priority, AppError, Result};
 
 #[derive(Debug, Deserialize)]
 pub struct ThumbQuery {
@@ -23,9 +23,11 @@ pub async fn thumb(
     State(state): State<Arc<AppState>>,
     Path(path): Path<String>,
     Query(query): Query<ThumbQuery>,
+    headers: HeaderMap,
 ) -> Result<Response> {
     let path = path.trim_matches('/');
     let width = query.w.unwrap_or(state.config.thumb_size);
+    let is_dir = fs::canonicalize_in_base(&state.config.base_dir_canonical, path)?.is_dir();
 
     // Try to get or generate thumbnail
     let thumb_path = thumbnails::get_or_build(
@@ -33,10 +35,46 @@ pub async fn thumb(
         path,
         width,
         state.config.ffmpeg_available,
+        Priority::Visible,
     )
     .await?;
 
     if let Some(thumb_path) = thumb_path {
+        // Folder previews change with their contents under the same URL, so browsers
+        // revalidate them against the cache key, which carries the folder version
+        if is_dir {
+            let etag = format!(
+                "\"{}\"",
+                thumb_path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy())
+                    .unwrap_or_default()
+            );
+            let builder = Response::builder()
+                .header(header::ETAG, &etag)
+                .header(header::CACHE_CONTROL, "no-cache");
+
+            let matches = headers
+                .get(header::IF_NONE_MATCH)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+            if matches {
+                return Ok(builder
+                    .status(StatusCode::NOT_MODIFIED)
+                    .body(Body::empty())
+                    .unwrap());
+            }
+
+            let metadata = tokio::fs::metadata(&thumb_path).await?;
+            let file = File::open(&thumb_path).await?;
+            return Ok(builder
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, "image/jpeg")
+                .header(header::CONTENT_LENGTH, metadata.len().to_string())
+                .body(Body::from_stream(ReaderStream::new(file)))
+                .unwrap());
+        }
+
         // Serve the thumbnail
         let metadata = tokio::fs::metadata(&thumb_path).await?;
         let file = File::open(&thumb_path).await?;
@@ -52,6 +90,12 @@ pub async fn thumb(
             .unwrap();
 
         Ok(response)
+    } else if is_dir {
+        // Folders without media keep their icon in the listing
+        Err(AppError::NotFound(format!(
+            "Nenhuma prévia para a pasta: {}",
+            path
+        )))
     } else {
         // No thumbnail available, return a simple gray placeholder
         let svg_content = r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="200" viewBox="0 0 200 200">
@@ -1,11 +1,16 @@
 use axum::{
     body::Body,
     extract::{Path, Query, State},
-    http::header,
-    response::{IntoResponse, Response},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
 };
+use futures::stream::Stream;
 use serde::Deserialize;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::controllers::AppState;
 use crate::models::{video_previews, Result};
@@ -13,6 +18,139 @@ use crate::models::{video_previews, Result};
 #[derive(Deserialize)]
 pub struct PreviewQuery {
     time: Option<f64>,
+    /// Index of a sprite sheet
+    sprite: Option<usize>,
+    /// Index of a scene-cut thumbnail
+    scene: Option<usize>,
+    /// Index of a container chapter, its thumbnail is built on demand
+    chapter: Option<usize>,
+    /// Any value returns the WebVTT thumbnails track
+    vtt: Option<String>,
+}
+
+/// Serve a cached preview file with a validator so players can revalidate cheaply
+async fn serve_cached(
+    file_path: &std::path::Path,
+    content_type: &str,
+    headers: &HeaderMap,
+) -> Result<Response> {
+    let metadata = match tokio::fs::metadata(file_path).await {
+        Ok(metadata) => metadata,
+        Err(_) => {
+            return Err(crate::models::AppError::NotFound(
+                "Arquivo de prévia não encontrado".to_string(),
+            ))
+        }
+    };
+
+    let modified = metadata
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let etag = format!("\"{:x}-{:x}\"", metadata.len(), modified);
+
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|tag| tag.trim() == etag));
+
+    let response = Response::builder()
+        .header(header::CACHE_CONTROL, "public, max-age=86400")
+        .header(header::ETAG, &etag);
+
+    if not_modified {
+        return Ok(response
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap());
+    }
+
+    let data = tokio::fs::read(file_path).await?;
+    Ok(response
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from(data))
+        .unwrap())
+}
+
+/// Serialize preview progress as an SSE event
+fn progress_event(name: &str, info: &video_previews::VideoPreviewInfo) -> Event {
+    Event::default()
+        .event(name)
+        .json_data(info)
+        .unwrap_or_else(|_| Event::default().event(name))
+}
+
+/// Stream generation progress of a video's previews until they are complete
+async fn preview_events(
+    state: &AppState,
+    path: &str,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, axum::Error>>>> {
+    let base_dir = state.config.base_dir_canonical.clone();
+    let ffmpeg_available = state.config.ffmpeg_available;
+    let scene_threshold = state.config.scene_threshold;
+
+    // Starts the job if needed, so opening the stream is enough to kick off generation
+    let mut info =
+        video_previews::get_or_start_previews(&base_dir, path, ffmpeg_available, scene_threshold)
+            .await?
+            .ok_or_else(|| {
+                crate::models::AppError::NotFound("Preview não disponível".to_string())
+            })?;
+    let mut progress_rx = video_previews::subscribe(&base_dir, path);
+
+    // The job may have finished between the two calls
+    if progress_rx.is_none() && !info.complete {
+        if let Some(latest) = video_previews::get_or_start_previews(
+            &base_dir,
+            path,
+            ffmpeg_available,
+            scene_threshold,
+        )
+        .await?
+        {
+            info = latest;
+        }
+        progress_rx = video_previews::subscribe(&base_dir, path);
+    }
+
+    let stream = async_stream::stream! {
+        if info.complete {
+            yield Ok(progress_event("complete", &info));
+            return;
+        }
+
+        yield Ok(progress_event("progress", &info));
+
+        let mut progress_rx = match progress_rx {
+            Some(rx) => rx,
+            None => return,
+        };
+
+        loop {
+            tokio::select! {
+                // Send periodic keep-alive
+                _ = tokio::time::sleep(Duration::from_secs(30)) => {
+                    yield Ok(Event::default().comment("keep-alive"));
+                }
+                changed = progress_rx.changed() => {
+                    // The job is gone without completing; the client can reconnect to restart it
+                    if changed.is_err() {
+                        break;
+                    }
+
+                    let info = progress_rx.borrow_and_update().clone();
+                    if info.complete {
+                        yield Ok(progress_event("complete", &info));
+                        break;
+                    }
+                    yield Ok(progress_event("progress", &info));
+                }
+            }
+        }
+    };
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
 }
 
 /// Serve video preview thumbnails
@@ -20,14 +158,23 @@ pub async fn video_preview(
     State(state): State<Arc<AppState>>,
     Path(path): Path<String>,
     Query(query): Query<PreviewQuery>,
-) -> Result<impl IntoResponse> {
+    headers: HeaderMap,
+) -> Result<Response> {
     let path = path.trim_matches('/');
 
-    // Get or build preview info
-    let preview_info = match video_previews::get_or_build_previews(
+    // `<video>/events` streams progress, unless it names an actual file
+    if let Some(video_path) = path.strip_suffix("/events") {
+        if !state.config.base_dir_canonical.join(path).is_file() {
+            return Ok(preview_events(&state, video_path).await?.into_response());
+        }
+    }
+
+    // Get preview info, starting generation in the background if needed
+    let preview_info = match video_previews::get_or_start_previews(
         &state.config.base_dir_canonical,
         path,
         state.config.ffmpeg_available,
+        state.config.scene_threshold,
     )
     .await?
     {
@@ -39,6 +186,50 @@ pub async fn video_preview(
         }
     };
 
+    // Sprite sheets and the WebVTT track exist once all frames are extracted
+    if let Some(index) = query.sprite {
+        let sprite_path = video_previews::get_sprite_path(
+            &state.config.base_dir_canonical,
+            path,
+            index,
+            &preview_info,
+        )
+        .ok_or_else(|| crate::models::AppError::NotFound("Sprite não encontrado".to_string()))?;
+        return serve_cached(&sprite_path, "image/jpeg", &headers).await;
+    }
+
+    if let Some(index) = query.scene {
+        let scene_path = video_previews::get_scene_path(
+            &state.config.base_dir_canonical,
+            path,
+            index,
+            &preview_info,
+        )
+        .ok_or_else(|| crate::models::AppError::NotFound("Cena não encontrada".to_string()))?;
+        return serve_cached(&scene_path, "image/jpeg", &headers).await;
+    }
+
+    if let Some(index) = query.chapter {
+        let chapter_path = video_previews::get_or_build_chapter_thumbnail(
+            &state.config.base_dir_canonical,
+            path,
+            index,
+            &preview_info,
+        )
+        .await?
+        .ok_or_else(|| crate::models::AppError::NotFound("Capítulo não encontrado".to_string()))?;
+        return serve_cached(&chapter_path, "image/jpeg", &headers).await;
+    }
+
+    if query.vtt.is_some() {
+        let vtt_path =
+            video_previews::get_vtt_path(&state.config.base_dir_canonical, path, &preview_info)
+                .ok_or_else(|| {
+                    crate::models::AppError::NotFound("Trilha VTT ainda não disponível".to_string())
+                })?;
+        return serve_cached(&vtt_path, "text/vtt; charset=utf-8", &headers).await;
+    }
+
     // If no specific time requested, return preview info as JSON
     let target_time = match query.time {
         Some(time) => time,
@@ -69,14 +260,5 @@ pub async fn video_preview(
     };
 
     // Read and serve thumbnail
-    match tokio::fs::read(&thumb_path).await {
-        Ok(data) => Ok(Response::builder()
-            .header(header::CONTENT_TYPE, "image/jpeg")
-            .header(header::CACHE_CONTROL, "public, max-age=86400")
-            .body(Body::from(data))
-            .unwrap()),
-        Err(_) => Err(crate::models::AppError::NotFound(
-            "Arquivo de miniatura não encontrado".to_string(),
-        )),
-    }
+    serve_cached(&thumb_path, "image/jpeg", &headers).await
 }
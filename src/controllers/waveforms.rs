@@ -0,0 +1,71 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio_util::io::ReaderStream;
+
+use crate::controllers::AppState;
+use crate::models::workers::Priority;
+use crate::models::{waveforms, AppError, Result};
+
+#[derive(Debug, Deserialize)]
+pub struct WaveformQuery {
+    /// `json` (default) for the peaks or `png` for a rendered image
+    #[serde(default)]
+    format: Option<String>,
+}
+
+/// Waveform of an audio file, as JSON peaks or a PNG image
+pub async fn waveform(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Query(query): Query<WaveformQuery>,
+) -> Result<Response> {
+    let path = path.trim_matches('/');
+    let base_dir = &state.config.base_dir_canonical;
+    let ffmpeg_available = state.config.ffmpeg_available;
+
+    match query.format.as_deref().unwrap_or("json") {
+        "json" => {
+            let waveform =
+                waveforms::get_or_build(base_dir, path, ffmpeg_available, Priority::Visible)
+                    .await?
+                    .ok_or_else(|| {
+                        AppError::NotFound(format!("Nenhuma forma de onda para: {}", path))
+                    })?;
+            Ok((
+                [(header::CACHE_CONTROL, "public, max-age=86400")],
+                Json(waveform),
+            )
+                .into_response())
+        }
+        "png" => {
+            let png_path = waveforms::get_or_build_png(base_dir, path, ffmpeg_available)
+                .await?
+                .ok_or_else(|| {
+                    AppError::NotFound(format!("Nenhuma forma de onda para: {}", path))
+                })?;
+
+            let metadata = tokio::fs::metadata(&png_path).await?;
+            let file = File::open(&png_path).await?;
+
+            let response = Response::builder()
+                .header(header::CONTENT_TYPE, "image/png")
+                .header(header::CONTENT_LENGTH, metadata.len().to_string())
+                .header(header::CACHE_CONTROL, "public, max-age=86400")
+                .body(Body::from_stream(ReaderStream::new(file)))
+                .unwrap();
+            Ok(response)
+        }
+        other => Err(AppError::BadRequest(format!(
+            "Formato de forma de onda inválido: {}",
+            other
+        ))),
+    }
+}
@@ -3,6 +3,7 @@ mod models;
 mod views;
 
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{get, post},
     Router,
 };
@@ -29,6 +30,21 @@ async fn main() -> anyhow::Result<()> {
     );
     tracing::info!("ffmpeg available: {}", config.ffmpeg_available);
 
+    // Size the thumbnail worker pools
+    models::workers::configure(config.image_workers, config.ffmpeg_workers);
+
+    // Start the background thumbnail crawler if requested
+    if config.pregenerate {
+        tracing::info!("Pre-generating thumbnails in the background");
+        tokio::spawn(models::thumbnails::pregenerate(
+            config.base_dir_canonical.clone(),
+            config.thumb_size,
+            config.ffmpeg_available,
+            config.show_hidden,
+            config.image_workers + config.ffmpeg_workers,
+        ));
+    }
+
     // Setup file watcher if enabled
     let state = if config.watch_enabled {
         tracing::info!("Watch mode enabled - monitoring file changes");
@@ -62,10 +78,40 @@ async fn main() -> anyhow::Result<()> {
         .route("/download/*path", get(controllers::download::download))
         .route("/content/*path", get(controllers::content::raw_content))
         .route("/thumbs/*path", get(controllers::thumbs::thumb))
+        .route("/image/*path", get(controllers::images::image))
+        .route("/meta/*path", get(controllers::meta::meta))
+        .route(
+            "/tags/*path",
+            get(controllers::tags::tags)
+                .post(controllers::tags::update_tags)
+                .layer(DefaultBodyLimit::max(controllers::tags::MAX_EDIT_BYTES)),
+        )
+        .route("/clip/*path", get(controllers::clips::clip))
+        .route("/frame/*path", get(controllers::frames::frame))
+        .route("/animation/*path", get(controllers::animations::animation))
+        .route(
+            "/contact-sheet/*path",
+            get(controllers::contact_sheets::contact_sheet),
+        )
+        .route("/waveform/*path", get(controllers::waveforms::waveform))
+        .route(
+            "/progress/*path",
+            post(controllers::progress::save_progress),
+        )
+        .route(
+            "/hover-previews/*path",
+            get(controllers::hover_previews::hover_preview),
+        )
         .route(
             "/video-previews/*path",
             get(controllers::video_previews::video_preview),
         )
+        .route("/music", get(controllers::music::artists))
+        .route("/music/albums", get(controllers::music::albums))
+        .route("/music/genres", get(controllers::music::genres))
+        .route("/music/artist/:id", get(controllers::music::artist))
+        .route("/music/album/:id", get(controllers::music::album))
+        .route("/music/genre/:id", get(controllers::music::genre))
         .route("/search/", get(controllers::search::search_page))
         .route("/search", get(controllers::search::search))
         .route("/upload/", post(controllers::upload::upload))
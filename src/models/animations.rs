@@ -0,0 +1,230 @@
+use anyhow::Result;
+use dashmap::DashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::models::workers::{self, Pool, Priority};
+
+type AnimationLocks = Arc<DashMap<String, Arc<Mutex<()>>>>;
+
+lazy_static::lazy_static! {
+    static ref ANIMATION_LOCKS: AnimationLocks = Arc::new(DashMap::new());
+}
+
+/// Longest segment that can be animated, in seconds
+pub const MAX_SECONDS: f64 = 15.0;
+
+/// Width bounds and default, in pixels
+pub const MIN_WIDTH: u32 = 64;
+pub const MAX_WIDTH: u32 = 1280;
+pub const DEFAULT_WIDTH: u32 = 480;
+
+/// Frame rate bounds and default
+pub const MAX_FPS: u32 = 30;
+pub const DEFAULT_FPS: u32 = 12;
+
+/// Animated output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    Gif,
+    Webp,
+}
+
+impl AnimationFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "gif" => Some(Self::Gif),
+            "webp" => Some(Self::Webp),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Webp => "webp",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Gif => "image/gif",
+            Self::Webp => "image/webp",
+        }
+    }
+}
+
+/// Normalized animation parameters
+#[derive(Debug, Clone, Copy)]
+pub struct AnimationParams {
+    /// Segment in seconds, rounded to milliseconds
+    pub start: f64,
+    pub end: f64,
+    pub width: u32,
+    pub fps: u32,
+    pub format: AnimationFormat,
+}
+
+impl AnimationParams {
+    /// Clamp width and frame rate; the range is checked by the caller
+    pub fn new(
+        start: f64,
+        end: f64,
+        width: Option<u32>,
+        fps: Option<u32>,
+        format: AnimationFormat,
+    ) -> Self {
+        let millis = |t: f64| (t * 1000.0).round() / 1000.0;
+        Self {
+            start: millis(start),
+            end: millis(end),
+            width: width.unwrap_or(DEFAULT_WIDTH).clamp(MIN_WIDTH, MAX_WIDTH),
+            fps: fps.unwrap_or(DEFAULT_FPS).clamp(1, MAX_FPS),
+            format,
+        }
+    }
+
+    fn cache_suffix(&self) -> String {
+        format!(
+            "{}-{}-w{}-f{}.{}",
+            (self.start * 1000.0) as u64,
+            (self.end * 1000.0) as u64,
+            self.width,
+            self.fps,
+            self.format.extension()
+        )
+    }
+}
+
+/// Get or build an animated GIF/WebP of a video segment.
+/// Returns `None` for files that are not videos and segments past the end.
+pub async fn get_or_build(
+    base_dir: &Path,
+    rel_path: &str,
+    params: AnimationParams,
+) -> Result<Option<PathBuf>> {
+    let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path)?;
+
+    let (_, media_kind) = crate::models::media::detect(&abs_path);
+    if !matches!(media_kind, crate::models::media::MediaKind::Video) {
+        return Ok(None);
+    }
+
+    // Version by size and mtime so a replaced video gets new animations
+    let metadata = tokio::fs::metadata(&abs_path).await?;
+    let modified = metadata
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let cache_key = format!(
+        "{}-{:x}-{:x}-{}",
+        blake3::hash(rel_path.as_bytes()),
+        metadata.len(),
+        modified,
+        params.cache_suffix()
+    );
+    let cache_dir = base_dir.join(".media-serve").join("animations");
+    let cache_path = cache_dir.join(&cache_key);
+
+    if cache_path.exists() {
+        return Ok(Some(cache_path));
+    }
+
+    let lock = ANIMATION_LOCKS
+        .entry(cache_key.clone())
+        .or_insert_with(|| Arc::new(Mutex::new(())))
+        .clone();
+
+    let _guard = lock.lock().await;
+
+    if cache_path.exists() {
+        return Ok(Some(cache_path));
+    }
+
+    tokio::fs::create_dir_all(&cache_dir).await?;
+
+    let _permit = workers::acquire(Pool::Ffmpeg, Priority::Visible).await;
+
+    let tmp_path = cache_dir.join(format!("{}.tmp.{}", cache_key, params.format.extension()));
+    match render(&abs_path, &tmp_path, params).await {
+        Ok(true) => {}
+        // The segment starts past the end of the video
+        Ok(false) => {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Ok(None);
+        }
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(e);
+        }
+    }
+    tokio::fs::rename(&tmp_path, &cache_path).await?;
+
+    Ok(Some(cache_path))
+}
+
+/// Render the segment with ffmpeg, `false` if it had no frames
+async fn render(src: &Path, dest: &Path, params: AnimationParams) -> Result<bool> {
+    let scale = format!("fps={},scale={}:-1:flags=lanczos", params.fps, params.width);
+
+    let mut command = tokio::process::Command::new("ffmpeg");
+    command
+        .arg("-y")
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-ss")
+        .arg(format!("{:.3}", params.start))
+        .arg("-t")
+        .arg(format!("{:.3}", params.end - params.start))
+        .arg("-i")
+        .arg(src)
+        .arg("-an")
+        .arg("-sn");
+
+    match params.format {
+        AnimationFormat::Gif => {
+            // A palette built from this very segment, only re-dithering the parts that change
+            command.arg("-filter_complex").arg(format!(
+                "{},split[a][b];[a]palettegen=stats_mode=diff[p];[b][p]paletteuse=dither=bayer:bayer_scale=5:diff_mode=rectangle",
+                scale
+            ));
+        }
+        AnimationFormat::Webp => {
+            command
+                .arg("-vf")
+                .arg(scale)
+                .arg("-c:v")
+                .arg("libwebp")
+                .arg("-lossless")
+                .arg("0")
+                .arg("-q:v")
+                .arg("70")
+                .arg("-compression_level")
+                .arg("6");
+        }
+    }
+
+    let output = command
+        .arg("-loop")
+        .arg("0")
+        .arg(dest)
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffmpeg failed: {}", stderr);
+    }
+
+    Ok(tokio::fs::metadata(dest)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0)
+        > 0)
+}
@@ -0,0 +1,210 @@
+use anyhow::Result;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use std::path::Path;
+use std::pin::Pin;
+use std::process::Stdio;
+use tokio_util::io::ReaderStream;
+
+use crate::models::workers::{self, Pool, Priority};
+
+/// Longest range that may be re-encoded in one request, in seconds; checked by the caller
+pub const MAX_REENCODE_SECONDS: f64 = 15.0 * 60.0;
+
+/// How the range is cut
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipMode {
+    /// Copy the streams untouched; fast, but the cut snaps to keyframes
+    Copy,
+    /// Decode and encode again (H.264/AAC) for a frame-accurate cut
+    Reencode,
+}
+
+impl ClipMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "copy" => Some(Self::Copy),
+            "reencode" => Some(Self::Reencode),
+            _ => None,
+        }
+    }
+}
+
+pub type ClipBody = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+/// A clip being produced by ffmpeg, streamed as it is written
+pub struct Clip {
+    pub filename: String,
+    pub content_type: &'static str,
+    pub body: ClipBody,
+}
+
+/// Output container for a clip: stream copies only stay in MP4 for MP4 sources, anything else
+/// goes to Matroska (or stays WebM) because codecs like VC-1 or PCM audio do not fit in MP4
+fn output_format(src: &Path, mode: ClipMode) -> (&'static str, &'static str, &'static str) {
+    let ext = src
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+
+    match (mode, ext.as_deref()) {
+        (ClipMode::Copy, Some("mp4" | "m4v")) | (ClipMode::Reencode, _) => {
+            ("mp4", "mp4", "video/mp4")
+        }
+        (ClipMode::Copy, Some("webm")) => ("webm", "webm", "video/webm"),
+        (ClipMode::Copy, _) => ("matroska", "mkv", "video/x-matroska"),
+    }
+}
+
+/// `01-02-03` style timestamp for file names
+fn filename_time(seconds: f64) -> String {
+    let total = seconds.max(0.0).floor() as u64;
+    format!(
+        "{:02}-{:02}-{:02}",
+        total / 3600,
+        (total % 3600) / 60,
+        total % 60
+    )
+}
+
+/// Start cutting `start..end` (seconds) out of a video.
+/// Returns `None` for files that are not videos and ranges past the end of the video.
+/// Waits for the first bytes of the clip, so ffmpeg refusing the input is an error here
+/// rather than an empty download.
+pub async fn start_clip(
+    base_dir: &Path,
+    rel_path: &str,
+    start: f64,
+    end: f64,
+    mode: ClipMode,
+) -> Result<Option<Clip>> {
+    let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path)?;
+
+    let (_, media_kind) = crate::models::media::detect(&abs_path);
+    if !matches!(media_kind, crate::models::media::MediaKind::Video) {
+        return Ok(None);
+    }
+
+    let duration = crate::models::video_previews::get_video_duration(&abs_path).await?;
+    let end = end.min(duration);
+    if start < 0.0 || end <= start {
+        return Ok(None);
+    }
+
+    let (format, extension, content_type) = output_format(&abs_path, mode);
+    let stem = abs_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("clip");
+    let filename = format!(
+        "{}_{}_{}.{}",
+        stem,
+        filename_time(start),
+        filename_time(end),
+        extension
+    );
+
+    let permit = workers::acquire(Pool::Ffmpeg, Priority::Visible).await;
+
+    let mut command = tokio::process::Command::new("ffmpeg");
+    command
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-ss")
+        .arg(format!("{:.3}", start))
+        .arg("-i")
+        .arg(&abs_path)
+        .arg("-t")
+        .arg(format!("{:.3}", end - start))
+        .arg("-map")
+        .arg("0:v:0")
+        .arg("-map")
+        .arg("0:a?")
+        .arg("-sn")
+        .arg("-dn");
+
+    match mode {
+        ClipMode::Copy => {
+            command
+                .arg("-c")
+                .arg("copy")
+                .arg("-avoid_negative_ts")
+                .arg("make_zero");
+        }
+        ClipMode::Reencode => {
+            command
+                .arg("-c:v")
+                .arg("libx264")
+                .arg("-preset")
+                .arg("veryfast")
+                .arg("-crf")
+                .arg("20")
+                .arg("-pix_fmt")
+                .arg("yuv420p")
+                .arg("-c:a")
+                .arg("aac")
+                .arg("-b:a")
+                .arg("160k");
+        }
+    }
+
+    // A pipe cannot be seeked back into, so MP4 is written fragmented
+    if format == "mp4" {
+        command
+            .arg("-movflags")
+            .arg("frag_keyframe+empty_moov+default_base_moof");
+    }
+
+    let mut child = command
+        .arg("-f")
+        .arg(format)
+        .arg("pipe:1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // A closed download stops the encode
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("ffmpeg stdout unavailable"))?;
+    let stderr = crate::models::video_previews::drain_stderr(&mut child);
+    let mut output = ReaderStream::new(stdout);
+
+    let first = match output.next().await {
+        Some(chunk) => chunk?,
+        None => {
+            let status = child.wait().await?;
+            let errors = stderr.await.unwrap_or_default();
+            anyhow::bail!("Clip export failed ({}): {}", status, errors.trim());
+        }
+    };
+
+    let body = async_stream::stream! {
+        let _permit = permit;
+        let mut child = child;
+
+        yield Ok(first);
+        while let Some(chunk) = output.next().await {
+            yield chunk;
+        }
+
+        let errors = stderr.await.unwrap_or_default();
+        match child.wait().await {
+            Ok(status) if !status.success() => {
+                tracing::warn!("Clip export failed ({}): {}", status, errors.trim());
+            }
+            Err(e) => tracing::warn!("Clip export failed: {}", e),
+            Ok(_) => {}
+        }
+    };
+
+    Ok(Some(Clip {
+        filename,
+        content_type,
+        body: Box::pin(body),
+    }))
+}
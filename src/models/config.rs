@@ -35,6 +35,38 @@ pub struct Cli {
     /// Enable file watching for auto-reload during development
     #[arg(long)]
     pub watch: bool,
+
+    /// Maximum concurrent image thumbnail jobs (default: number of CPUs)
+    #[arg(long = "image-workers")]
+    pub image_workers: Option<usize>,
+
+    /// Maximum concurrent ffmpeg thumbnail jobs
+    #[arg(long = "ffmpeg-workers", default_value_t = 2)]
+    pub ffmpeg_workers: usize,
+
+    /// Pre-generate thumbnails for the whole tree in the background
+    #[arg(long)]
+    pub pregenerate: bool,
+
+    /// Play short animated previews when hovering videos in grid view (requires ffmpeg)
+    #[arg(long = "hover-previews")]
+    pub hover_previews: bool,
+
+    /// Show RAW files with a same-named JPEG as a single item
+    #[arg(long = "pair-raw")]
+    pub pair_raw: bool,
+
+    /// Also take video preview thumbnails at scene cuts (requires ffmpeg)
+    #[arg(long = "scene-detection")]
+    pub scene_detection: bool,
+
+    /// Scene change score (0-1) above which a frame counts as a cut
+    #[arg(long = "scene-threshold", default_value_t = 0.3)]
+    pub scene_threshold: f64,
+
+    /// Allow editing audio tags and covers from the file page (rewrites the files)
+    #[arg(long = "tag-editing")]
+    pub tag_editing: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -45,6 +77,15 @@ pub struct AppConfig {
     pub show_hidden: bool,
     pub ffmpeg_available: bool,
     pub watch_enabled: bool,
+    pub image_workers: usize,
+    pub ffmpeg_workers: usize,
+    pub pregenerate: bool,
+    pub hover_previews: bool,
+    pub pair_raw: bool,
+    /// Scene cut threshold when scene detection is enabled
+    pub scene_threshold: Option<f64>,
+    /// Whether audio tags can be edited from the file page
+    pub tag_editing: bool,
 }
 
 impl AppConfig {
@@ -77,6 +118,17 @@ impl AppConfig {
             show_hidden: cli.show_hidden,
             ffmpeg_available,
             watch_enabled: cli.watch,
+            image_workers: cli
+                .image_workers
+                .unwrap_or_else(crate::models::workers::default_image_workers)
+                .max(1),
+            ffmpeg_workers: cli.ffmpeg_workers.max(1),
+            pregenerate: cli.pregenerate,
+            hover_previews: cli.hover_previews && ffmpeg_available,
+            pair_raw: cli.pair_raw,
+            scene_threshold: (cli.scene_detection && ffmpeg_available)
+                .then_some(cli.scene_threshold.clamp(0.0, 1.0)),
+            tag_editing: cli.tag_editing,
         })
     }
 }
@@ -0,0 +1,375 @@
+use anyhow::Result;
+use dashmap::DashMap;
+use image::{DynamicImage, Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::models::workers::{self, Pool, Priority};
+
+type SheetLocks = Arc<DashMap<String, Arc<Mutex<()>>>>;
+
+lazy_static::lazy_static! {
+    static ref SHEET_LOCKS: SheetLocks = Arc::new(DashMap::new());
+}
+
+/// Grid bounds and defaults
+pub const MAX_COLUMNS: u32 = 10;
+pub const MAX_ROWS: u32 = 10;
+pub const DEFAULT_COLUMNS: u32 = 4;
+pub const DEFAULT_ROWS: u32 = 6;
+
+/// Sheet width bounds and default, in pixels
+pub const MIN_WIDTH: u32 = 640;
+pub const MAX_WIDTH: u32 = 4096;
+pub const DEFAULT_WIDTH: u32 = 1600;
+
+/// Space around and between frames
+const GAP: u32 = 8;
+
+/// Tallest frame relative to its width, so portrait videos do not make huge sheets
+const MAX_TILE_RATIO: f64 = 2.0;
+
+/// Tall grids get narrower frames rather than a sheet taller than this
+const MAX_HEIGHT: u32 = 8192;
+
+const BACKGROUND: Rgba<u8> = Rgba([15, 23, 42, 255]);
+
+const JPEG_QUALITY: u8 = 85;
+
+/// Generic families resolve to fonts that are often missing (Arial, Courier New),
+/// so the usual free fonts are named first
+const SANS_FONTS: &str = "DejaVu Sans, Liberation Sans, Noto Sans, Arial, sans-serif";
+const MONO_FONTS: &str =
+    "DejaVu Sans Mono, Liberation Mono, Noto Sans Mono, Courier New, monospace";
+
+/// Normalized contact sheet parameters
+#[derive(Debug, Clone, Copy)]
+pub struct SheetParams {
+    pub columns: u32,
+    pub rows: u32,
+    pub width: u32,
+}
+
+impl SheetParams {
+    /// Clamp the grid and width to the supported bounds
+    pub fn new(columns: Option<u32>, rows: Option<u32>, width: Option<u32>) -> Self {
+        Self {
+            columns: columns.unwrap_or(DEFAULT_COLUMNS).clamp(1, MAX_COLUMNS),
+            rows: rows.unwrap_or(DEFAULT_ROWS).clamp(1, MAX_ROWS),
+            width: width.unwrap_or(DEFAULT_WIDTH).clamp(MIN_WIDTH, MAX_WIDTH),
+        }
+    }
+
+    fn frame_count(&self) -> u32 {
+        self.columns * self.rows
+    }
+}
+
+/// Pixel geometry of a sheet
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    width: u32,
+    height: u32,
+    header_height: u32,
+    tile_width: u32,
+    tile_height: u32,
+}
+
+impl Layout {
+    /// Fit the grid in the requested width, keeping the video aspect ratio (`height / width`)
+    fn new(params: SheetParams, ratio: f64) -> Self {
+        let ratio = ratio.clamp(0.1, MAX_TILE_RATIO);
+        let header_height = (params.width / 20).clamp(56, 160);
+
+        let mut tile_width = (params.width - GAP * (params.columns + 1)) / params.columns;
+        let max_tile_height = (MAX_HEIGHT - header_height - GAP * (params.rows + 1)) / params.rows;
+        if tile_width as f64 * ratio > max_tile_height as f64 {
+            tile_width = (max_tile_height as f64 / ratio) as u32;
+        }
+        // Even sizes keep ffmpeg's scaler happy
+        let tile_width = tile_width.max(2) & !1;
+        let tile_height = ((tile_width as f64 * ratio).round() as u32).max(2) & !1;
+
+        Self {
+            width: tile_width * params.columns + GAP * (params.columns + 1),
+            height: header_height + tile_height * params.rows + GAP * (params.rows + 1),
+            header_height,
+            tile_width,
+            tile_height,
+        }
+    }
+}
+
+/// What the header says about the video
+struct SheetHeader {
+    name: String,
+    duration: f64,
+    resolution: Option<(u32, u32)>,
+}
+
+/// Get or build the contact sheet of a video: a grid of evenly spaced frames
+/// with their timestamps under a header with the name, duration and resolution.
+/// Returns `None` for files that are not videos or whose duration is unknown.
+pub async fn get_or_build(
+    base_dir: &Path,
+    rel_path: &str,
+    params: SheetParams,
+) -> Result<Option<PathBuf>> {
+    let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path)?;
+
+    let (_, media_kind) = crate::models::media::detect(&abs_path);
+    if !matches!(media_kind, crate::models::media::MediaKind::Video) {
+        return Ok(None);
+    }
+
+    // Version by size and mtime so a replaced video gets a new sheet
+    let metadata = tokio::fs::metadata(&abs_path).await?;
+    let modified = metadata
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let cache_key = format!(
+        "{}-{:x}-{:x}-{}x{}-w{}.jpg",
+        blake3::hash(rel_path.as_bytes()),
+        metadata.len(),
+        modified,
+        params.columns,
+        params.rows,
+        params.width
+    );
+    let cache_dir = base_dir.join(".media-serve").join("contact-sheets");
+    let cache_path = cache_dir.join(&cache_key);
+
+    if cache_path.exists() {
+        return Ok(Some(cache_path));
+    }
+
+    let lock = SHEET_LOCKS
+        .entry(cache_key.clone())
+        .or_insert_with(|| Arc::new(Mutex::new(())))
+        .clone();
+
+    let _guard = lock.lock().await;
+
+    if cache_path.exists() {
+        return Ok(Some(cache_path));
+    }
+
+    // A failed probe still leaves get_video_duration to try
+    let summary = crate::models::probe::get_or_probe(base_dir, rel_path, true)
+        .await
+        .ok()
+        .flatten()
+        .as_ref()
+        .map(crate::models::probe::MediaSummary::from)
+        .unwrap_or_default();
+    let duration = match summary.duration {
+        Some(duration) => duration,
+        None => crate::models::video_previews::get_video_duration(&abs_path).await?,
+    };
+    if !duration.is_finite() || duration <= 0.0 {
+        return Ok(None);
+    }
+
+    let header = SheetHeader {
+        name: abs_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        duration,
+        resolution: summary.width.zip(summary.height),
+    };
+
+    let ratio = header
+        .resolution
+        .map(|(w, h)| h as f64 / w as f64)
+        .unwrap_or(9.0 / 16.0);
+    let layout = Layout::new(params, ratio);
+
+    // Frames from the middle of each slice, skipping the black first frame
+    let count = params.frame_count();
+    let times: Vec<f64> = (0..count)
+        .map(|i| duration * (i as f64 + 0.5) / count as f64)
+        .collect();
+
+    let frames_dir = cache_dir.join(format!("{}.frames", cache_key));
+    let tmp_path = cache_dir.join(format!("{}.tmp", cache_key));
+    tokio::fs::create_dir_all(&frames_dir).await?;
+
+    let result = async {
+        let mut frames = Vec::with_capacity(times.len());
+        {
+            let _permit = workers::acquire(Pool::Ffmpeg, Priority::Visible).await;
+            for (index, &time) in times.iter().enumerate() {
+                let frame = frames_dir.join(format!("frame_{:03}.jpg", index));
+                crate::models::video_previews::extract_tile(
+                    &abs_path,
+                    time,
+                    layout.tile_width,
+                    layout.tile_height,
+                    &frame,
+                )
+                .await?;
+                frames.push(frame);
+            }
+        }
+
+        let _permit = workers::acquire(Pool::Image, Priority::Visible).await;
+        let dest = tmp_path.clone();
+        tokio::task::spawn_blocking(move || {
+            compose(&header, &frames, &times, params.columns, layout, &dest)
+        })
+        .await??;
+        tokio::fs::rename(&tmp_path, &cache_path).await?;
+        anyhow::Ok(())
+    }
+    .await;
+
+    let _ = tokio::fs::remove_dir_all(&frames_dir).await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+    }
+    result?;
+
+    Ok(Some(cache_path))
+}
+
+/// Lay the frames out under the header and write the sheet as JPEG
+fn compose(
+    header: &SheetHeader,
+    frames: &[PathBuf],
+    times: &[f64],
+    columns: u32,
+    layout: Layout,
+    dest: &Path,
+) -> Result<()> {
+    let Layout {
+        width,
+        height,
+        header_height,
+        tile_width,
+        tile_height,
+    } = layout;
+    let mut sheet = RgbaImage::from_pixel(width, height, BACKGROUND);
+
+    let header_image = render_svg(
+        &header_svg(header, width, header_height),
+        width,
+        header_height,
+    )?;
+    image::imageops::overlay(&mut sheet, &header_image, 0, 0);
+
+    let label_size = (tile_height / 9).clamp(10, 28);
+    for (index, (frame, &time)) in frames.iter().zip(times).enumerate() {
+        let index = index as u32;
+        let x = GAP + (index % columns) * (tile_width + GAP);
+        let y = header_height + GAP + (index / columns) * (tile_height + GAP);
+
+        // A frame past the real end of the stream stays blank
+        if let Ok(mut tile) = image::open(frame) {
+            if tile.width() != tile_width || tile.height() != tile_height {
+                tile = tile.resize_exact(
+                    tile_width,
+                    tile_height,
+                    image::imageops::FilterType::Triangle,
+                );
+            }
+            image::imageops::replace(&mut sheet, &tile.to_rgba8(), x as i64, y as i64);
+        }
+
+        let (svg, label_width, label_height) =
+            label_svg(&crate::models::fs::format_duration(time), label_size);
+        let label = render_svg(&svg, label_width, label_height)?;
+        let margin = label_size as i64 / 3;
+        image::imageops::overlay(
+            &mut sheet,
+            &label,
+            (x + tile_width) as i64 - label.width() as i64 - margin,
+            (y + tile_height) as i64 - label.height() as i64 - margin,
+        );
+    }
+
+    let rgb = DynamicImage::ImageRgba8(sheet).to_rgb8();
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(dest)?);
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut writer, JPEG_QUALITY)
+        .encode_image(&rgb)?;
+    Ok(())
+}
+
+/// Rasterize an overlay document of `width`x`height` at its own size
+fn render_svg(svg: &str, width: u32, height: u32) -> Result<RgbaImage> {
+    Ok(crate::models::svg::rasterize_data(svg.as_bytes(), width.max(height))?.to_rgba8())
+}
+
+fn header_svg(header: &SheetHeader, width: u32, height: u32) -> String {
+    let mut details = vec![format!(
+        "Duração {}",
+        crate::models::fs::format_duration(header.duration)
+    )];
+    if let Some((w, h)) = header.resolution {
+        details.push(format!("{}×{}", w, h));
+    }
+
+    let title_size = height as f64 * 0.34;
+    let detail_size = height as f64 * 0.24;
+
+    // Cut long names instead of letting them run off the sheet
+    let max_chars = ((width - GAP * 2) as f64 / (title_size * 0.6)) as usize;
+    let name = if header.name.chars().count() > max_chars {
+        let cut: String = header
+            .name
+            .chars()
+            .take(max_chars.saturating_sub(1))
+            .collect();
+        format!("{}…", cut)
+    } else {
+        header.name.clone()
+    };
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}">
+<text x="{gap}" y="{title_y:.1}" font-family="{fonts}" font-weight="bold" font-size="{title_size:.1}" fill="#f1f5f9">{name}</text>
+<text x="{gap}" y="{detail_y:.1}" font-family="{fonts}" font-size="{detail_size:.1}" fill="#94a3b8">{details}</text>
+</svg>"##,
+        w = width,
+        h = height,
+        gap = GAP,
+        title_y = height as f64 * 0.44,
+        detail_y = height as f64 * 0.82,
+        name = escape_xml(&name),
+        details = escape_xml(&details.join(" · ")),
+        fonts = SANS_FONTS,
+    )
+}
+
+/// Timestamp badge, returned with its size
+fn label_svg(text: &str, font_size: u32) -> (String, u32, u32) {
+    // Monospace digits have a predictable width
+    let padding = font_size / 3;
+    let width = (text.chars().count() as f64 * font_size as f64 * 0.62).ceil() as u32 + padding * 2;
+    let height = font_size + padding * 2;
+    let svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}">
+<rect width="{w}" height="{h}" rx="{padding}" fill="#000000" fill-opacity="0.65"/>
+<text x="{x}" y="{y:.1}" font-family="{fonts}" font-size="{font_size}" fill="#ffffff" text-anchor="middle">{text}</text>
+</svg>"##,
+        w = width,
+        h = height,
+        x = width / 2,
+        y = padding as f64 + font_size as f64 * 0.82,
+        text = escape_xml(text),
+        fonts = MONO_FONTS,
+    );
+    (svg, width, height)
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,70 @@
+use lofty::file::TaggedFileExt;
+use lofty::picture::{Picture, PictureType};
+use std::path::{Path, PathBuf};
+
+/// File stems recognized as cover images, in order of preference
+const COVER_STEMS: &[&str] = &["cover", "folder", ".cover"];
+
+/// Image extensions accepted for cover files
+const COVER_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+/// Find cover art for an audio file: embedded picture first, then a cover file in its directory
+pub fn find_audio_cover(audio_path: &Path) -> Option<Vec<u8>> {
+    if let Some(data) = embedded_cover(audio_path) {
+        return Some(data);
+    }
+
+    let dir = audio_path.parent()?;
+    let cover = find_cover_file(dir)?;
+    std::fs::read(cover).ok()
+}
+
+/// Read the embedded picture from ID3 (APIC), FLAC (PICTURE) or MP4 (covr) tags
+pub fn embedded_cover(audio_path: &Path) -> Option<Vec<u8>> {
+    let tagged_file = match lofty::read_from_path(audio_path) {
+        Ok(file) => file,
+        Err(e) => {
+            tracing::debug!("Failed to read tags from {}: {}", audio_path.display(), e);
+            return None;
+        }
+    };
+
+    let pictures: Vec<&Picture> = tagged_file
+        .tags()
+        .iter()
+        .flat_map(|tag| tag.pictures())
+        .collect();
+
+    // Prefer the front cover, otherwise take whatever picture is there
+    pictures
+        .iter()
+        .find(|p| p.pic_type() == PictureType::CoverFront)
+        .or_else(|| pictures.first())
+        .map(|p| p.data().to_vec())
+}
+
+/// Find a `cover.*`, `folder.*` or `.cover` image in a directory (case-insensitive)
+pub fn find_cover_file(dir: &Path) -> Option<PathBuf> {
+    let mut candidates: Vec<(usize, PathBuf)> = std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let stem = path.file_stem()?.to_str()?.to_lowercase();
+            let rank = COVER_STEMS.iter().position(|s| *s == stem)?;
+
+            // A bare `.cover` file is sniffed by content, everything else needs an image extension
+            let ext_ok = match path.extension().and_then(|e| e.to_str()) {
+                Some(ext) => COVER_EXTENSIONS.contains(&ext.to_lowercase().as_str()),
+                None => stem == ".cover",
+            };
+            if !ext_ok || !path.is_file() {
+                return None;
+            }
+            Some((rank, path))
+        })
+        .collect();
+
+    candidates.sort();
+    candidates.into_iter().next().map(|(_, path)| path)
+}
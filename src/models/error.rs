@@ -29,6 +29,9 @@ pub enum AppError {
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("Internal server error: {0}")]
     Internal(String),
 
@@ -86,6 +89,20 @@ impl IntoResponse for AppError {
                         Some(format!("Erro técnico: {}", msg)),
                     )
                 }
+                AppError::Conflict(msg) => {
+                    tracing::warn!("Conflict: {}", msg);
+                    (
+                        StatusCode::CONFLICT,
+                        "Arquivo Modificado".to_string(),
+                        msg.clone(),
+                        "conflict".to_string(),
+                        vec![
+                            "Recarregue a página para ver a versão atual".to_string(),
+                            "Refaça as alterações e envie novamente".to_string(),
+                        ],
+                        Some(format!("Erro técnico: {}", msg)),
+                    )
+                }
                 AppError::Internal(msg) => {
                     tracing::error!("Internal error: {}", msg);
                     (
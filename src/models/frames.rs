@@ -0,0 +1,140 @@
+use anyhow::Result;
+use std::path::Path;
+use std::process::Stdio;
+
+use crate::models::workers::{self, Pool, Priority};
+
+/// Widest frame that will be produced when a width is requested
+pub const MAX_FRAME_WIDTH: u32 = 7680;
+
+/// Encoded format of an extracted frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    Png,
+    Jpeg,
+}
+
+impl FrameFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "jpeg" | "jpg" => Some(Self::Jpeg),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+        }
+    }
+
+    /// ffmpeg encoder writing this format
+    fn codec(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "mjpeg",
+        }
+    }
+}
+
+/// A single decoded frame
+pub struct Frame {
+    pub data: Vec<u8>,
+    /// Suggested file name, e.g. `video_00-01-02.500.png`
+    pub filename: String,
+}
+
+/// Extract the frame shown at `time` seconds, at full resolution or scaled down to `width`.
+/// Returns `None` for files that are not videos and times past the end.
+pub async fn extract_frame(
+    base_dir: &Path,
+    rel_path: &str,
+    time: f64,
+    format: FrameFormat,
+    width: Option<u32>,
+) -> Result<Option<Frame>> {
+    let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path)?;
+
+    let (_, media_kind) = crate::models::media::detect(&abs_path);
+    if !matches!(media_kind, crate::models::media::MediaKind::Video) {
+        return Ok(None);
+    }
+
+    let _permit = workers::acquire(Pool::Ffmpeg, Priority::Visible).await;
+
+    let mut command = tokio::process::Command::new("ffmpeg");
+    command
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        // Input seeking decodes from the previous keyframe up to the exact time
+        .arg("-ss")
+        .arg(format!("{:.3}", time))
+        .arg("-i")
+        .arg(&abs_path)
+        .arg("-frames:v")
+        .arg("1")
+        .arg("-an")
+        .arg("-sn");
+
+    // Never upscale, only cap the width
+    if let Some(width) = width {
+        command.arg("-vf").arg(format!(
+            "scale='min({},iw)':-2",
+            width.clamp(1, MAX_FRAME_WIDTH)
+        ));
+    }
+
+    if format == FrameFormat::Jpeg {
+        command.arg("-q:v").arg("2");
+    }
+
+    let output = command
+        .arg("-c:v")
+        .arg(format.codec())
+        .arg("-f")
+        .arg("image2pipe")
+        .arg("pipe:1")
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffmpeg failed: {}", stderr);
+    }
+
+    // Seeking past the end succeeds without writing anything
+    if output.stdout.is_empty() {
+        return Ok(None);
+    }
+
+    let stem = abs_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("frame");
+    let millis = (time * 1000.0).round() as u64;
+    let filename = format!(
+        "{}_{:02}-{:02}-{:02}.{:03}.{}",
+        stem,
+        millis / 3_600_000,
+        (millis / 60_000) % 60,
+        (millis / 1000) % 60,
+        millis % 1000,
+        format.extension()
+    );
+
+    Ok(Some(Frame {
+        data: output.stdout,
+        filename,
+    }))
+}
@@ -216,6 +216,18 @@ pub fn format_size(size: u64) -> String {
     }
 }
 
+/// Format a duration in seconds as `h:mm:ss` or `m:ss`
+pub fn format_duration(seconds: f64) -> String {
+    let total = seconds.max(0.0).round() as u64;
+    let (hours, minutes, secs) = (total / 3600, (total % 3600) / 60, total % 60);
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{}:{:02}", minutes, secs)
+    }
+}
+
 /// Format datetime for display
 pub fn format_datetime(dt: &OffsetDateTime) -> String {
     let format = time::format_description::parse("[year]-[month]-[day] [hour]:[minute]:[second]")
@@ -0,0 +1,179 @@
+use anyhow::Result;
+use dashmap::DashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::models::workers::{self, Pool, Priority};
+
+type HoverLocks = Arc<DashMap<String, Arc<Mutex<()>>>>;
+
+lazy_static::lazy_static! {
+    static ref HOVER_LOCKS: HoverLocks = Arc::new(DashMap::new());
+}
+
+/// Number of clips sampled across the video
+const SAMPLE_COUNT: usize = 4;
+
+/// Length of each sampled clip in seconds
+const SAMPLE_SECONDS: f64 = 1.0;
+
+/// Frame rate of the generated preview
+const PREVIEW_FPS: u32 = 12;
+
+/// Get or build a short muted looping MP4 preview for a video
+pub async fn get_or_build(
+    base_dir: &Path,
+    rel_path: &str,
+    width: u32,
+    ffmpeg_available: bool,
+) -> Result<Option<PathBuf>> {
+    if !ffmpeg_available {
+        return Ok(None);
+    }
+
+    let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path)?;
+
+    let (_, media_kind) = crate::models::media::detect(&abs_path);
+    if !matches!(media_kind, crate::models::media::MediaKind::Video) {
+        return Ok(None);
+    }
+
+    // Stored next to the static thumbnails
+    let cache_key = format!("{}-w{}-hover", blake3::hash(rel_path.as_bytes()), width);
+    let cache_dir = base_dir.join(".media-serve").join("thumbs");
+    let cache_path = cache_dir.join(format!("{}.mp4", cache_key));
+
+    // Videos ffmpeg could not preview leave a marker, versioned so a replaced file is retried
+    let metadata = tokio::fs::metadata(&abs_path).await?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let failed_marker = cache_dir.join(format!(
+        "{}-{:x}-{:x}.failed",
+        cache_key,
+        metadata.len(),
+        modified
+    ));
+
+    if cache_path.exists() {
+        return Ok(Some(cache_path));
+    }
+    if failed_marker.exists() {
+        return Ok(None);
+    }
+
+    let lock = HOVER_LOCKS
+        .entry(cache_key.clone())
+        .or_insert_with(|| Arc::new(Mutex::new(())))
+        .clone();
+
+    let _guard = lock.lock().await;
+
+    if cache_path.exists() {
+        return Ok(Some(cache_path));
+    }
+    if failed_marker.exists() {
+        return Ok(None);
+    }
+
+    tokio::fs::create_dir_all(&cache_dir).await?;
+
+    let _permit = workers::acquire(Pool::Hover, Priority::Visible).await;
+
+    // Write to a temp file so a half-written preview is never served
+    let tmp_path = cache_dir.join(format!("{}.tmp.mp4", cache_key));
+    let result = match crate::models::video_previews::get_video_duration(&abs_path).await {
+        Ok(duration) => generate_hover_preview(&abs_path, &tmp_path, duration, width).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+        tracing::warn!("Failed to generate hover preview: {}", e);
+        tokio::fs::write(&failed_marker, b"").await?;
+        return Ok(None);
+    }
+    tokio::fs::rename(&tmp_path, &cache_path).await?;
+
+    Ok(Some(cache_path))
+}
+
+/// Start times of the sampled clips, spread across the video
+fn sample_times(duration: f64) -> Vec<f64> {
+    // Short videos are previewed from the start as a single clip
+    if duration <= SAMPLE_SECONDS * SAMPLE_COUNT as f64 * 2.0 {
+        return vec![0.0];
+    }
+
+    (1..=SAMPLE_COUNT)
+        .map(|i| duration * i as f64 / (SAMPLE_COUNT + 1) as f64)
+        .collect()
+}
+
+/// Cut one short clip per sample time and concatenate them with ffmpeg
+async fn generate_hover_preview(src: &Path, dest: &Path, duration: f64, width: u32) -> Result<()> {
+    let times = sample_times(duration);
+    let clip_length = if times.len() == 1 {
+        duration.clamp(SAMPLE_SECONDS, SAMPLE_SECONDS * SAMPLE_COUNT as f64)
+    } else {
+        SAMPLE_SECONDS
+    };
+
+    let mut command = tokio::process::Command::new("ffmpeg");
+    command.arg("-y").arg("-loglevel").arg("error");
+
+    for time in &times {
+        command
+            .arg("-ss")
+            .arg(format!("{:.3}", time))
+            .arg("-t")
+            .arg(format!("{:.3}", clip_length))
+            .arg("-i")
+            .arg(src);
+    }
+
+    // Normalize every clip, then join them into one stream
+    let mut filter = String::new();
+    for index in 0..times.len() {
+        filter.push_str(&format!(
+            "[{}:v]fps={},scale='min({},iw)':-2,setsar=1[v{}];",
+            index, PREVIEW_FPS, width, index
+        ));
+    }
+    for index in 0..times.len() {
+        filter.push_str(&format!("[v{}]", index));
+    }
+    filter.push_str(&format!("concat=n={}:v=1:a=0[out]", times.len()));
+
+    let output = command
+        .arg("-filter_complex")
+        .arg(filter)
+        .arg("-map")
+        .arg("[out]")
+        .arg("-an")
+        .arg("-c:v")
+        .arg("libx264")
+        .arg("-preset")
+        .arg("veryfast")
+        .arg("-crf")
+        .arg("30")
+        .arg("-pix_fmt")
+        .arg("yuv420p")
+        .arg("-movflags")
+        .arg("+faststart")
+        .arg("-f")
+        .arg("mp4")
+        .arg(dest)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffmpeg failed: {}", stderr);
+    }
+
+    Ok(())
+}
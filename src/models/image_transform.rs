@@ -0,0 +1,267 @@
+use anyhow::Result;
+use dashmap::DashMap;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::models::workers::{self, Pool, Priority};
+
+type ImageLocks = Arc<DashMap<String, Arc<Mutex<()>>>>;
+
+lazy_static::lazy_static! {
+    static ref IMAGE_LOCKS: ImageLocks = Arc::new(DashMap::new());
+}
+
+/// Requested sizes are rounded up to one of these so `srcset` variants share cache entries
+const SIZE_BUCKETS: &[u32] = &[
+    64, 128, 256, 320, 480, 640, 768, 1024, 1280, 1600, 1920, 2560, 3200, 3840,
+];
+
+/// Largest width or height that will ever be produced
+pub const MAX_DIMENSION: u32 = 3840;
+
+/// Default JPEG quality
+const DEFAULT_QUALITY: u8 = 80;
+
+/// How the image is fitted into the requested box
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fit {
+    /// Fill the box, cropping the overflow
+    Cover,
+    /// Fit inside the box, keeping the aspect ratio
+    Contain,
+    /// Stretch to the exact box
+    Fill,
+}
+
+impl Fit {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "cover" => Some(Self::Cover),
+            "contain" => Some(Self::Contain),
+            "fill" => Some(Self::Fill),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cover => "cover",
+            Self::Contain => "contain",
+            Self::Fill => "fill",
+        }
+    }
+}
+
+/// Encoded output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl OutputFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "jpeg" | "jpg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            "webp" => Some(Self::Webp),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Webp => "webp",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Webp => "image/webp",
+        }
+    }
+}
+
+/// Normalized transform parameters
+#[derive(Debug, Clone, Copy)]
+pub struct TransformParams {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fit: Fit,
+    pub format: OutputFormat,
+    /// Only used for JPEG; PNG and WebP are lossless
+    pub quality: u8,
+}
+
+impl TransformParams {
+    /// Clamp and bucket raw request values
+    pub fn new(
+        width: Option<u32>,
+        height: Option<u32>,
+        fit: Fit,
+        format: OutputFormat,
+        quality: Option<u8>,
+    ) -> Self {
+        let quality = match format {
+            // Steps of 5 keep the number of cached variants small
+            OutputFormat::Jpeg => {
+                let q = quality.unwrap_or(DEFAULT_QUALITY).clamp(10, 100);
+                ((q as u32 + 2) / 5 * 5) as u8
+            }
+            OutputFormat::Png | OutputFormat::Webp => 0,
+        };
+
+        Self {
+            width: width.filter(|w| *w > 0).map(bucket),
+            height: height.filter(|h| *h > 0).map(bucket),
+            fit,
+            format,
+            quality,
+        }
+    }
+
+    fn cache_suffix(&self) -> String {
+        let dim = |d: Option<u32>| d.map(|v| v.to_string()).unwrap_or_else(|| "auto".into());
+        format!(
+            "{}x{}-{}-q{}.{}",
+            dim(self.width),
+            dim(self.height),
+            self.fit.as_str(),
+            self.quality,
+            self.format.extension()
+        )
+    }
+}
+
+/// Round a size up to the nearest bucket
+fn bucket(size: u32) -> u32 {
+    SIZE_BUCKETS
+        .iter()
+        .copied()
+        .find(|b| *b >= size)
+        .unwrap_or(MAX_DIMENSION)
+}
+
+/// Get or build a transformed copy of an image
+pub async fn get_or_build(
+    base_dir: &Path,
+    rel_path: &str,
+    params: TransformParams,
+) -> Result<Option<PathBuf>> {
+    let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path)?;
+
+    let (_, media_kind) = crate::models::media::detect(&abs_path);
+    if !matches!(media_kind, crate::models::media::MediaKind::Image) {
+        return Ok(None);
+    }
+
+    // Version by size and mtime so edited originals get new variants
+    let metadata = tokio::fs::metadata(&abs_path).await?;
+    let modified = metadata
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let cache_key = format!(
+        "{}-{:x}-{:x}-{}",
+        blake3::hash(rel_path.as_bytes()),
+        metadata.len(),
+        modified,
+        params.cache_suffix()
+    );
+    let cache_dir = base_dir.join(".media-serve").join("images");
+    let cache_path = cache_dir.join(&cache_key);
+
+    if cache_path.exists() {
+        return Ok(Some(cache_path));
+    }
+
+    let lock = IMAGE_LOCKS
+        .entry(cache_key.clone())
+        .or_insert_with(|| Arc::new(Mutex::new(())))
+        .clone();
+
+    let _guard = lock.lock().await;
+
+    if cache_path.exists() {
+        return Ok(Some(cache_path));
+    }
+
+    tokio::fs::create_dir_all(&cache_dir).await?;
+
+    let _permit = workers::acquire(Pool::Image, Priority::Visible).await;
+
+    let dest = cache_path.clone();
+    tokio::task::spawn_blocking(move || transform(&abs_path, &dest, params)).await??;
+
+    Ok(Some(cache_path))
+}
+
+/// Decode, resize and encode an image into `dest`
+fn transform(src: &Path, dest: &Path, params: TransformParams) -> Result<()> {
+    // Vector images are rendered straight at the requested size
+    let render_size = params.width.max(params.height).unwrap_or(MAX_DIMENSION);
+    let img = crate::models::thumbnails::load_image_at(src, render_size)?;
+    let filter = image::imageops::FilterType::Lanczos3;
+
+    let (src_width, src_height) = (img.width(), img.height());
+    let resized = match (params.width, params.height) {
+        (None, None) => img.resize(MAX_DIMENSION, MAX_DIMENSION, filter),
+        (Some(w), None) => img.resize(w, MAX_DIMENSION, filter),
+        (None, Some(h)) => img.resize(MAX_DIMENSION, h, filter),
+        (Some(w), Some(h)) => match params.fit {
+            Fit::Cover => img.resize_to_fill(w, h, filter),
+            Fit::Contain => img.resize(w, h, filter),
+            Fit::Fill => img.resize_exact(w, h, filter),
+        },
+    };
+
+    // Only an explicit cover/fill box may enlarge the original
+    let exact_box = params.width.is_some() && params.height.is_some() && params.fit != Fit::Contain;
+    let resized = if !exact_box && (resized.width() > src_width || resized.height() > src_height) {
+        img
+    } else {
+        resized
+    };
+
+    // Encoded into a temp file so a half-written image is never served
+    let tmp = dest.with_extension(format!("tmp.{}", params.format.extension()));
+    let result = encode(&resized, &tmp, params.format, params.quality)
+        .and_then(|()| Ok(std::fs::rename(&tmp, dest)?));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Encode `img` into a new file at `path`
+fn encode(img: &image::DynamicImage, path: &Path, format: OutputFormat, quality: u8) -> Result<()> {
+    let mut writer = BufWriter::new(std::fs::File::create(path)?);
+
+    match format {
+        OutputFormat::Jpeg => {
+            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut writer, quality);
+            image::DynamicImage::ImageRgb8(crate::models::thumbnails::flatten(img))
+                .write_with_encoder(encoder)?;
+        }
+        OutputFormat::Png => {
+            let encoder = image::codecs::png::PngEncoder::new(&mut writer);
+            img.write_with_encoder(encoder)?;
+        }
+        OutputFormat::Webp => {
+            let encoder = image::codecs::webp::WebPEncoder::new_lossless(&mut writer);
+            image::DynamicImage::ImageRgba8(img.to_rgba8()).write_with_encoder(encoder)?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
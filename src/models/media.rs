@@ -27,7 +27,7 @@ impl MediaKind {
 
     /// Check if this media type should show thumbnails
     pub fn has_thumbnail(&self) -> bool {
-        matches!(self, Self::Image | Self::Video)
+        matches!(self, Self::Image | Self::Video | Self::Audio)
     }
 }
 
@@ -38,13 +38,21 @@ pub fn folder_icon() -> &'static str {
 
 /// Detect MIME type and media kind from file path
 pub fn detect(path: &Path) -> (String, MediaKind) {
+    // Content sniffing sees SVG as plain XML
+    if crate::models::svg::is_svg(path) {
+        return ("image/svg+xml".to_string(), MediaKind::Image);
+    }
+
+    // RAW containers are mostly sniffed as TIFF or not at all
+    if let Some(mime) = crate::models::raw::raw_mime(path) {
+        return (mime.to_string(), MediaKind::Image);
+    }
+
     // Try to detect from file content first
-    if let Ok(kind) = infer::get_from_path(path) {
-        if let Some(mime_type) = kind {
-            let mime_str = mime_type.mime_type();
-            let kind = media_kind_from_mime(mime_str);
-            return (mime_str.to_string(), kind);
-        }
+    if let Ok(Some(mime_type)) = infer::get_from_path(path) {
+        let mime_str = mime_type.mime_type();
+        let kind = media_kind_from_mime(mime_str);
+        return (mime_str.to_string(), kind);
     }
 
     // Fallback to guessing from extension
@@ -1,10 +1,27 @@
+pub mod animations;
+pub mod clips;
 pub mod config;
+pub mod contact_sheets;
+pub mod cover_art;
 pub mod error;
+pub mod frames;
 pub mod fs;
+pub mod hover_previews;
+pub mod image_transform;
 pub mod media;
+pub mod music;
+pub mod placeholders;
+pub mod poster;
+pub mod probe;
+pub mod progress;
+pub mod raw;
+pub mod svg;
+pub mod tags;
 pub mod thumbnails;
 pub mod video_previews;
 pub mod watcher;
+pub mod waveforms;
+pub mod workers;
 
 pub use config::{AppConfig, Cli};
 pub use error::{AppError, Result};
@@ -0,0 +1,428 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::models::tags::{self, AudioTags};
+
+lazy_static::lazy_static! {
+    /// Last complete index, replaced as a whole when a rebuild finishes
+    static ref LIBRARY: RwLock<Option<Arc<MusicLibrary>>> = RwLock::new(None);
+}
+
+/// Set while a build runs in the background
+static BUILDING: AtomicBool = AtomicBool::new(false);
+
+/// Set when tags were edited after the running or last build started
+static OUTDATED: AtomicBool = AtomicBool::new(false);
+
+/// Audio files seen by the running build, shown while the first index is built
+static SCANNED: AtomicUsize = AtomicUsize::new(0);
+
+/// An index older than this is rebuilt in the background on the next visit
+const REFRESH_AFTER: Duration = Duration::from_secs(10 * 60);
+
+const UNKNOWN_ARTIST: &str = "Artista desconhecido";
+const VARIOUS_ARTISTS: &str = "Vários artistas";
+const NO_ALBUM: &str = "Sem álbum";
+
+/// A track of the music index
+#[derive(Debug, Clone)]
+pub struct MusicTrack {
+    /// Path relative to the base directory
+    pub path: String,
+    pub encoded_path: String,
+    /// Tag title, or the file name without extension
+    pub title: String,
+    pub artist: Option<String>,
+    pub disc: Option<u32>,
+    pub track: Option<u32>,
+    pub duration: Option<f64>,
+}
+
+impl MusicTrack {
+    pub fn formatted_duration(&self) -> Option<String> {
+        self.duration.map(crate::models::fs::format_duration)
+    }
+}
+
+/// Tracks sharing an album tag (or, untagged, a folder)
+#[derive(Debug, Clone)]
+pub struct MusicAlbum {
+    pub id: String,
+    pub title: String,
+    pub artist: String,
+    pub artist_id: String,
+    pub year: Option<u32>,
+    pub genre: Option<String>,
+    /// In disc, then track number order
+    pub tracks: Vec<MusicTrack>,
+}
+
+impl MusicAlbum {
+    /// Encoded path of the track whose thumbnail stands for the album cover
+    pub fn cover_path(&self) -> &str {
+        self.tracks
+            .first()
+            .map(|track| track.encoded_path.as_str())
+            .unwrap_or_default()
+    }
+
+    pub fn formatted_duration(&self) -> Option<String> {
+        let total: f64 = self.tracks.iter().filter_map(|t| t.duration).sum();
+        (total > 0.0).then(|| crate::models::fs::format_duration(total))
+    }
+
+    /// Whether the tracks span more than one disc
+    pub fn has_discs(&self) -> bool {
+        self.tracks
+            .iter()
+            .filter_map(|t| t.disc)
+            .any(|disc| disc > 1)
+    }
+}
+
+/// Albums credited to one artist
+#[derive(Debug, Clone)]
+pub struct MusicArtist {
+    pub id: String,
+    pub name: String,
+    /// Indexes into `MusicLibrary::albums`, oldest first
+    pub albums: Vec<usize>,
+    pub track_count: usize,
+}
+
+/// Albums sharing a genre
+#[derive(Debug, Clone)]
+pub struct MusicGenre {
+    pub id: String,
+    pub name: String,
+    /// Indexes into `MusicLibrary::albums`
+    pub albums: Vec<usize>,
+}
+
+/// Artist, album and genre index of every audio file under the base directory
+#[derive(Debug)]
+pub struct MusicLibrary {
+    pub artists: Vec<MusicArtist>,
+    /// Sorted by artist, year and title
+    pub albums: Vec<MusicAlbum>,
+    pub genres: Vec<MusicGenre>,
+    pub track_count: usize,
+    built: Instant,
+    album_index: HashMap<String, usize>,
+}
+
+impl MusicLibrary {
+    pub fn album(&self, id: &str) -> Option<&MusicAlbum> {
+        self.album_index.get(id).map(|&index| &self.albums[index])
+    }
+
+    pub fn artist(&self, id: &str) -> Option<&MusicArtist> {
+        self.artists.iter().find(|artist| artist.id == id)
+    }
+
+    pub fn genre(&self, id: &str) -> Option<&MusicGenre> {
+        self.genres.iter().find(|genre| genre.id == id)
+    }
+
+    /// Albums at the given indexes
+    pub fn albums_at(&self, indexes: &[usize]) -> Vec<&MusicAlbum> {
+        indexes.iter().map(|&index| &self.albums[index]).collect()
+    }
+}
+
+/// Short stable ID for URLs
+fn make_id(key: &str) -> String {
+    blake3::hash(key.as_bytes()).to_hex()[..16].to_string()
+}
+
+/// The current index, starting a background build when there is none yet or it is stale.
+/// Returns `None` until the first build finishes; see `scanned` for its progress.
+pub fn current(base_dir: &Path, show_hidden: bool) -> Option<Arc<MusicLibrary>> {
+    let library = LIBRARY.read().unwrap().clone();
+
+    let stale = library
+        .as_ref()
+        .is_none_or(|library| library.built.elapsed() > REFRESH_AFTER)
+        || OUTDATED.load(Ordering::Acquire);
+    if stale && !BUILDING.swap(true, Ordering::AcqRel) {
+        let base_dir = base_dir.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            // Cleared even if the build panics, so the next visit can retry
+            let _building = BuildingGuard;
+            OUTDATED.store(false, Ordering::Release);
+            let started = Instant::now();
+            SCANNED.store(0, Ordering::Relaxed);
+            let library = build(&base_dir, show_hidden);
+            tracing::info!(
+                "Music index built: {} tracks in {} albums ({:.1}s)",
+                library.track_count,
+                library.albums.len(),
+                started.elapsed().as_secs_f64()
+            );
+            *LIBRARY.write().unwrap() = Some(Arc::new(library));
+        });
+    }
+
+    library
+}
+
+/// Rebuild the index on the next visit, e.g. after tags were edited
+pub fn invalidate() {
+    OUTDATED.store(true, Ordering::Release);
+}
+
+struct BuildingGuard;
+
+impl Drop for BuildingGuard {
+    fn drop(&mut self) {
+        BUILDING.store(false, Ordering::Release);
+    }
+}
+
+/// Audio files read so far by the running build
+pub fn scanned() -> usize {
+    SCANNED.load(Ordering::Relaxed)
+}
+
+/// List every audio file with its metadata, skipping our own caches
+fn collect_audio_files(base_dir: &Path, show_hidden: bool) -> Vec<(PathBuf, std::fs::Metadata)> {
+    let mut out = Vec::new();
+    let mut stack = vec![base_dir.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+
+            if name == ".media-serve" || name == ".video-previews" {
+                continue;
+            }
+            if !show_hidden && crate::models::fs::is_hidden(&name) {
+                continue;
+            }
+
+            let file_type = match entry.file_type() {
+                Ok(t) => t,
+                Err(_) => continue,
+            };
+
+            if file_type.is_dir() {
+                stack.push(entry.path());
+            } else if file_type.is_file() && tags::is_audio_path(&entry.path()) {
+                if let Ok(metadata) = entry.metadata() {
+                    out.push((entry.path(), metadata));
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Tracks of one album while the index is being built
+struct AlbumBuilder {
+    title: String,
+    album_artist: Option<String>,
+    tracks: Vec<(MusicTrack, AudioTags)>,
+}
+
+/// Walk the base directory and group its audio files by artist, album and genre
+fn build(base_dir: &Path, show_hidden: bool) -> MusicLibrary {
+    let mut groups: HashMap<String, AlbumBuilder> = HashMap::new();
+    let mut track_count = 0;
+
+    for (abs_path, metadata) in collect_audio_files(base_dir, show_hidden) {
+        let rel_path = match crate::models::fs::relative_from_base(base_dir, &abs_path) {
+            Ok(rel) => rel,
+            Err(_) => continue,
+        };
+        // Files with unreadable tags are still listed, by name and folder
+        let tags = tags::get_or_read(base_dir, &rel_path, &abs_path, &metadata).unwrap_or_default();
+        SCANNED.fetch_add(1, Ordering::Relaxed);
+        track_count += 1;
+
+        let mut folder = Path::new(&rel_path)
+            .parent()
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        // `Album/CD1` and `Album/CD2` are one album, named after `Album`
+        let folder_disc = Path::new(&folder)
+            .file_name()
+            .and_then(|name| disc_folder_number(&name.to_string_lossy()));
+        if folder_disc.is_some() {
+            folder = Path::new(&folder)
+                .parent()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default();
+        }
+        let title = tags
+            .album
+            .clone()
+            .or_else(|| {
+                Path::new(&folder)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| NO_ALBUM.to_string());
+
+        // Without an album artist, a compilation is held together by its folder
+        let key = format!(
+            "{}\0{}",
+            tags.album_artist
+                .as_deref()
+                .map(str::to_lowercase)
+                .unwrap_or_else(|| folder.clone()),
+            title.to_lowercase()
+        );
+
+        let track = MusicTrack {
+            encoded_path: crate::models::fs::url_encode_path(&rel_path),
+            title: tags.title.clone().unwrap_or_else(|| {
+                abs_path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| rel_path.clone())
+            }),
+            path: rel_path,
+            artist: tags.artist.clone(),
+            disc: tags.disc.or(folder_disc),
+            track: tags.track,
+            duration: tags.duration,
+        };
+
+        groups
+            .entry(key)
+            .or_insert_with(|| AlbumBuilder {
+                title,
+                album_artist: tags.album_artist.clone(),
+                tracks: Vec::new(),
+            })
+            .tracks
+            .push((track, tags));
+    }
+
+    let mut albums: Vec<MusicAlbum> = groups
+        .into_iter()
+        .map(|(key, group)| finish_album(&key, group))
+        .collect();
+    albums.sort_by(|a, b| {
+        a.artist
+            .to_lowercase()
+            .cmp(&b.artist.to_lowercase())
+            .then(a.year.cmp(&b.year))
+            .then(a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+    });
+
+    let mut artists: HashMap<String, MusicArtist> = HashMap::new();
+    let mut genres: HashMap<String, MusicGenre> = HashMap::new();
+    for (index, album) in albums.iter().enumerate() {
+        let artist = artists
+            .entry(album.artist_id.clone())
+            .or_insert_with(|| MusicArtist {
+                id: album.artist_id.clone(),
+                name: album.artist.clone(),
+                albums: Vec::new(),
+                track_count: 0,
+            });
+        artist.albums.push(index);
+        artist.track_count += album.tracks.len();
+
+        if let Some(genre) = &album.genre {
+            let id = make_id(&genre.to_lowercase());
+            genres
+                .entry(id.clone())
+                .or_insert_with(|| MusicGenre {
+                    id,
+                    name: genre.clone(),
+                    albums: Vec::new(),
+                })
+                .albums
+                .push(index);
+        }
+    }
+
+    let mut artists: Vec<MusicArtist> = artists.into_values().collect();
+    artists.sort_by_key(|artist| artist.name.to_lowercase());
+    let mut genres: Vec<MusicGenre> = genres.into_values().collect();
+    genres.sort_by_key(|genre| genre.name.to_lowercase());
+
+    let album_index = albums
+        .iter()
+        .enumerate()
+        .map(|(index, album)| (album.id.clone(), index))
+        .collect();
+
+    MusicLibrary {
+        artists,
+        albums,
+        genres,
+        track_count,
+        built: Instant::now(),
+        album_index,
+    }
+}
+
+/// Disc number of a `CD2`, `Disc 2` or `Disco 2` style folder
+fn disc_folder_number(name: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    let number = ["disco", "disc", "disk", "cd"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))?;
+    number.trim_start_matches([' ', '-', '_', '.']).parse().ok()
+}
+
+/// Order the tracks and settle the album artist, year and genre
+fn finish_album(key: &str, group: AlbumBuilder) -> MusicAlbum {
+    let mut entries = group.tracks;
+    entries.sort_by(|(a, _), (b, _)| {
+        a.disc
+            .unwrap_or(1)
+            .cmp(&b.disc.unwrap_or(1))
+            .then(
+                a.track
+                    .unwrap_or(u32::MAX)
+                    .cmp(&b.track.unwrap_or(u32::MAX)),
+            )
+            .then(a.path.cmp(&b.path))
+    });
+
+    let artist = group.album_artist.unwrap_or_else(|| {
+        let mut names = entries
+            .iter()
+            .filter_map(|(_, tags)| tags.artist.as_deref());
+        match names.next() {
+            Some(first) if names.all(|name| name.eq_ignore_ascii_case(first)) => first.to_string(),
+            Some(_) => VARIOUS_ARTISTS.to_string(),
+            None => UNKNOWN_ARTIST.to_string(),
+        }
+    });
+
+    let year = entries.iter().filter_map(|(_, tags)| tags.year).min();
+
+    // The genre most tracks agree on
+    let mut genre_counts: HashMap<&str, usize> = HashMap::new();
+    for genre in entries.iter().filter_map(|(_, tags)| tags.genre.as_deref()) {
+        *genre_counts.entry(genre).or_default() += 1;
+    }
+    let genre = genre_counts
+        .into_iter()
+        .max_by(|(a_name, a), (b_name, b)| a.cmp(b).then(b_name.cmp(a_name)))
+        .map(|(genre, _)| genre.to_string());
+
+    MusicAlbum {
+        id: make_id(key),
+        title: group.title,
+        artist_id: make_id(&artist.to_lowercase()),
+        artist,
+        year,
+        genre,
+        tracks: entries.into_iter().map(|(track, _)| track).collect(),
+    }
+}
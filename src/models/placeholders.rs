@@ -0,0 +1,82 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Number of BlurHash components along each axis
+const BLURHASH_X: u32 = 4;
+const BLURHASH_Y: u32 = 3;
+
+/// Images are shrunk to this size before hashing, the result is blurry anyway
+const SAMPLE_SIZE: u32 = 32;
+
+/// Compact stand-in painted while the real thumbnail loads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Placeholder {
+    pub blurhash: String,
+    /// Average color as `#rrggbb`
+    pub color: String,
+}
+
+/// Path of the placeholder stored next to a cached thumbnail
+fn sidecar_path(thumb_path: &Path) -> PathBuf {
+    thumb_path.with_extension("json")
+}
+
+/// Read the placeholder stored for a cached thumbnail
+pub fn read(thumb_path: &Path) -> Option<Placeholder> {
+    let content = std::fs::read_to_string(sidecar_path(thumb_path)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Compute and store the placeholder for a thumbnail unless it already exists
+pub async fn ensure(thumb_path: &Path) -> Result<()> {
+    let sidecar = sidecar_path(thumb_path);
+    if sidecar.exists() {
+        return Ok(());
+    }
+
+    let thumb_path = thumb_path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let placeholder = compute(&thumb_path)?;
+        let tmp = sidecar.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string(&placeholder)?)?;
+        std::fs::rename(&tmp, &sidecar)?;
+        Ok::<_, anyhow::Error>(())
+    })
+    .await??;
+
+    Ok(())
+}
+
+/// Compute BlurHash and average color from an image file
+fn compute(path: &Path) -> Result<Placeholder> {
+    let img = image::open(path)?
+        .thumbnail(SAMPLE_SIZE, SAMPLE_SIZE)
+        .to_rgba8();
+
+    let blurhash = blurhash::encode(
+        BLURHASH_X,
+        BLURHASH_Y,
+        img.width(),
+        img.height(),
+        img.as_raw(),
+    )
+    .map_err(|e| anyhow::anyhow!("blurhash failed: {:?}", e))?;
+
+    let pixel_count = (img.width() * img.height()).max(1) as u64;
+    let mut sum = [0u64; 3];
+    for pixel in img.pixels() {
+        for (channel, total) in sum.iter_mut().enumerate() {
+            *total += pixel[channel] as u64;
+        }
+    }
+
+    let color = format!(
+        "#{:02x}{:02x}{:02x}",
+        sum[0] / pixel_count,
+        sum[1] / pixel_count,
+        sum[2] / pixel_count
+    );
+
+    Ok(Placeholder { blurhash, color })
+}
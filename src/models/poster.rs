@@ -0,0 +1,135 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+/// Candidate positions for the poster frame, as fractions of the duration
+const CANDIDATE_POSITIONS: &[f64] = &[0.1, 0.25, 0.4, 0.55, 0.7, 0.85];
+
+/// Videos shorter than this (in seconds) just use the middle frame
+const SHORT_VIDEO_SECONDS: f64 = 5.0;
+
+/// Size of the grayscale frames used for scoring
+const SAMPLE_WIDTH: u32 = 64;
+const SAMPLE_HEIGHT: u32 = 36;
+
+/// Image extensions accepted for poster sidecars
+const SIDECAR_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+/// Find an explicit poster next to the video, e.g. `video.poster.jpg` for `video.mp4`.
+/// Only the fixed candidate names are checked, so cache hits cost a few stats, not a folder scan.
+pub fn find_sidecar(video_path: &Path) -> Option<PathBuf> {
+    let stem = video_path.file_stem()?.to_str()?;
+
+    SIDECAR_EXTENSIONS
+        .iter()
+        .flat_map(|ext| [ext.to_string(), ext.to_uppercase()])
+        .map(|ext| video_path.with_file_name(format!("{}.poster.{}", stem, ext)))
+        .find(|path| path.is_file())
+}
+
+/// Whether the sidecar was changed after the cached thumbnail was written
+pub fn sidecar_newer_than(video_path: &Path, cache_path: &Path) -> bool {
+    let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+
+    match find_sidecar(video_path) {
+        Some(sidecar) => match (modified(&sidecar), modified(cache_path)) {
+            (Some(sidecar_time), Some(cache_time)) => sidecar_time > cache_time,
+            _ => false,
+        },
+        None => false,
+    }
+}
+
+/// Pick the timestamp of the most informative frame among several candidates
+pub async fn pick_poster_time(video_path: &Path) -> Result<f64> {
+    let duration = crate::models::video_previews::get_video_duration(video_path).await?;
+
+    if duration < SHORT_VIDEO_SECONDS {
+        return Ok(duration / 2.0);
+    }
+
+    let mut best: Option<(f64, f64)> = None;
+
+    for position in CANDIDATE_POSITIONS {
+        let time = duration * position;
+        let score = match sample_frame(video_path, time).await {
+            Ok(pixels) => score_frame(&pixels),
+            Err(e) => {
+                tracing::debug!("Failed to sample frame at {:.2}s: {}", time, e);
+                continue;
+            }
+        };
+
+        if best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((time, score));
+        }
+    }
+
+    Ok(best.map(|(time, _)| time).unwrap_or(duration / 2.0))
+}
+
+/// Decode a single small grayscale frame at `time`
+async fn sample_frame(video_path: &Path, time: f64) -> Result<Vec<u8>> {
+    let output = tokio::process::Command::new("ffmpeg")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-ss")
+        .arg(format!("{:.3}", time))
+        .arg("-i")
+        .arg(video_path)
+        .arg("-frames:v")
+        .arg("1")
+        .arg("-vf")
+        .arg(format!("scale={}:{}", SAMPLE_WIDTH, SAMPLE_HEIGHT))
+        .arg("-pix_fmt")
+        .arg("gray")
+        .arg("-f")
+        .arg("rawvideo")
+        .arg("-")
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffmpeg failed: {}", stderr);
+    }
+
+    if output.stdout.len() < (SAMPLE_WIDTH * SAMPLE_HEIGHT) as usize {
+        anyhow::bail!("ffmpeg returned an incomplete frame");
+    }
+
+    Ok(output.stdout)
+}
+
+/// Score a grayscale frame: high entropy is good, near-black or near-white frames are not
+fn score_frame(pixels: &[u8]) -> f64 {
+    let mut histogram = [0usize; 32];
+    let mut sum = 0u64;
+
+    for &p in pixels {
+        histogram[(p / 8) as usize] += 1;
+        sum += p as u64;
+    }
+
+    let total = pixels.len() as f64;
+    let mean = sum as f64 / total;
+
+    let entropy: f64 = histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum();
+
+    // Fades and title cards are mostly black or white
+    let brightness = if !(24.0..=232.0).contains(&mean) {
+        0.1
+    } else {
+        1.0 - ((mean - 128.0).abs() / 128.0) * 0.5
+    };
+
+    entropy * brightness
+}
@@ -0,0 +1,556 @@
+use anyhow::Result;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::models::workers::{self, Pool, Priority};
+
+type ProbeLocks = Arc<DashMap<String, Arc<Mutex<()>>>>;
+
+lazy_static::lazy_static! {
+    static ref PROBE_LOCKS: ProbeLocks = Arc::new(DashMap::new());
+}
+
+/// Technical details of an audio or video file, as reported by ffprobe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaMetadata {
+    /// Container format, e.g. `matroska,webm` or `mov,mp4,m4a,3gp,3g2,mj2`
+    pub container: String,
+    pub container_name: Option<String>,
+    /// Duration in seconds
+    pub duration: Option<f64>,
+    /// Overall bit rate in bits per second
+    pub bit_rate: Option<u64>,
+    pub video_streams: Vec<VideoStream>,
+    pub audio_streams: Vec<AudioStream>,
+    pub subtitle_streams: Vec<SubtitleStream>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoStream {
+    pub codec: String,
+    pub profile: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    /// Average frames per second
+    pub frame_rate: Option<f64>,
+    pub bit_rate: Option<u64>,
+    pub pixel_format: Option<String>,
+    /// HDR formats found in the stream, e.g. `HDR10`, `HLG` or `Dolby Vision`
+    pub hdr: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioStream {
+    pub codec: String,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub bit_rate: Option<u64>,
+    pub language: Option<String>,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubtitleStream {
+    pub codec: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Duration and pixel size shown in directory listings
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct MediaSummary {
+    /// Duration in seconds, for audio and video
+    pub duration: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl MediaSummary {
+    pub fn formatted_duration(&self) -> Option<String> {
+        self.duration.map(crate::models::fs::format_duration)
+    }
+
+    pub fn resolution(&self) -> Option<String> {
+        Some(format!("{}×{}", self.width?, self.height?))
+    }
+
+    /// Pixel count, used to sort by resolution
+    pub fn pixels(&self) -> Option<u64> {
+        Some(self.width? as u64 * self.height? as u64)
+    }
+}
+
+impl From<&MediaMetadata> for MediaSummary {
+    fn from(metadata: &MediaMetadata) -> Self {
+        let video = metadata.video_streams.first();
+        Self {
+            duration: metadata.duration,
+            width: video.map(|v| v.width).filter(|w| *w > 0),
+            height: video.map(|v| v.height).filter(|h| *h > 0),
+        }
+    }
+}
+
+impl MediaMetadata {
+    pub fn formatted_duration(&self) -> Option<String> {
+        self.duration.map(crate::models::fs::format_duration)
+    }
+
+    pub fn formatted_bit_rate(&self) -> Option<String> {
+        self.bit_rate.map(format_bit_rate)
+    }
+}
+
+impl VideoStream {
+    /// One-line description for the file page
+    pub fn summary(&self) -> String {
+        let mut parts = vec![match &self.profile {
+            Some(profile) => format!("{} ({})", self.codec, profile),
+            None => self.codec.clone(),
+        }];
+        parts.push(format!("{}×{}", self.width, self.height));
+        if let Some(fps) = self.frame_rate {
+            if fps.fract().abs() < 0.001 {
+                parts.push(format!("{:.0} fps", fps));
+            } else {
+                parts.push(format!("{:.3} fps", fps));
+            }
+        }
+        if let Some(bit_rate) = self.bit_rate {
+            parts.push(format_bit_rate(bit_rate));
+        }
+        if !self.hdr.is_empty() {
+            parts.push(self.hdr.join(", "));
+        }
+        parts.join(" · ")
+    }
+}
+
+impl AudioStream {
+    /// One-line description for the file page
+    pub fn summary(&self) -> String {
+        let mut parts = vec![self.codec.clone()];
+        match (&self.channel_layout, self.channels) {
+            (Some(layout), _) => parts.push(layout.clone()),
+            (None, Some(channels)) => parts.push(format!("{} canais", channels)),
+            (None, None) => {}
+        }
+        if let Some(sample_rate) = self.sample_rate {
+            parts.push(format!("{} kHz", sample_rate as f64 / 1000.0));
+        }
+        if let Some(bit_rate) = self.bit_rate {
+            parts.push(format_bit_rate(bit_rate));
+        }
+        parts.extend(self.language.clone());
+        parts.extend(self.title.clone());
+        parts.join(" · ")
+    }
+}
+
+impl SubtitleStream {
+    /// One-line description for the file page
+    pub fn summary(&self) -> String {
+        let mut parts = vec![self.codec.clone()];
+        parts.extend(self.language.clone());
+        parts.extend(self.title.clone());
+        parts.join(" · ")
+    }
+}
+
+/// Format a bit rate for display
+pub fn format_bit_rate(bits_per_second: u64) -> String {
+    if bits_per_second >= 1_000_000 {
+        format!("{:.1} Mb/s", bits_per_second as f64 / 1_000_000.0)
+    } else {
+        format!("{} kb/s", bits_per_second / 1000)
+    }
+}
+
+/// Cache file name stem of a file version, keyed by path, size and mtime
+fn cache_key(rel_path: &str, size: u64, modified: u64) -> String {
+    format!(
+        "{}-{:x}-{:x}",
+        blake3::hash(rel_path.as_bytes()),
+        size,
+        modified
+    )
+}
+
+fn cache_dir(base_dir: &Path) -> PathBuf {
+    base_dir.join(".media-serve").join("meta")
+}
+
+/// Path of the cached metadata of a file, versioned by size and mtime
+fn cache_path(base_dir: &Path, rel_path: &str, metadata: &std::fs::Metadata) -> PathBuf {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    cache_dir(base_dir).join(format!(
+        "{}.json",
+        cache_key(rel_path, metadata.len(), modified)
+    ))
+}
+
+/// Path of the cached dimensions of an image
+fn image_cache_path(base_dir: &Path, rel_path: &str, metadata: &std::fs::Metadata) -> PathBuf {
+    cache_path(base_dir, rel_path, metadata).with_extension("image.json")
+}
+
+/// Read the listing summary of a file if it was already probed, without touching the file itself.
+/// `size` and `modified` (Unix seconds) come from the directory listing.
+pub fn cached_summary(
+    base_dir: &Path,
+    rel_path: &str,
+    size: u64,
+    modified: u64,
+    media_kind: crate::models::media::MediaKind,
+) -> Option<MediaSummary> {
+    let key = cache_key(rel_path, size, modified);
+    match media_kind {
+        crate::models::media::MediaKind::Video | crate::models::media::MediaKind::Audio => {
+            let content =
+                std::fs::read_to_string(cache_dir(base_dir).join(format!("{}.json", key))).ok()?;
+            let metadata: MediaMetadata = serde_json::from_str(&content).ok()?;
+            Some(MediaSummary::from(&metadata))
+        }
+        crate::models::media::MediaKind::Image => {
+            let content =
+                std::fs::read_to_string(cache_dir(base_dir).join(format!("{}.image.json", key)))
+                    .ok()?;
+            serde_json::from_str(&content).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Get or build the listing summary of an image, audio or video file
+pub async fn get_or_build_summary(
+    base_dir: &Path,
+    rel_path: &str,
+    ffmpeg_available: bool,
+) -> Result<Option<MediaSummary>> {
+    let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path)?;
+
+    let (_, media_kind) = crate::models::media::detect(&abs_path);
+    if !matches!(media_kind, crate::models::media::MediaKind::Image) {
+        let metadata = get_or_probe(base_dir, rel_path, ffmpeg_available).await?;
+        return Ok(metadata.as_ref().map(MediaSummary::from));
+    }
+
+    let metadata = tokio::fs::metadata(&abs_path).await?;
+    let cache_path = image_cache_path(base_dir, rel_path, &metadata);
+
+    if let Some(cached) = read_summary_file(&cache_path).await {
+        return Ok(Some(cached));
+    }
+
+    let lock = PROBE_LOCKS
+        .entry(cache_path.to_string_lossy().to_string())
+        .or_insert_with(|| Arc::new(Mutex::new(())))
+        .clone();
+
+    let _guard = lock.lock().await;
+
+    if let Some(cached) = read_summary_file(&cache_path).await {
+        return Ok(Some(cached));
+    }
+
+    let _permit = workers::acquire(Pool::Image, Priority::Background).await;
+
+    let summary = tokio::task::spawn_blocking(move || {
+        // Unreadable headers still get a cache entry so they are not retried on every listing
+        let (width, height) = match image_dimensions(&abs_path) {
+            Ok(Some((width, height))) => (Some(width), Some(height)),
+            Ok(None) => (None, None),
+            Err(e) => {
+                tracing::debug!("Failed to read dimensions of {}: {}", abs_path.display(), e);
+                (None, None)
+            }
+        };
+        MediaSummary {
+            duration: None,
+            width,
+            height,
+        }
+    })
+    .await?;
+
+    if let Some(cache_dir) = cache_path.parent() {
+        tokio::fs::create_dir_all(cache_dir).await?;
+    }
+    let tmp = cache_path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, serde_json::to_string(&summary)?).await?;
+    tokio::fs::rename(&tmp, &cache_path).await?;
+
+    Ok(Some(summary))
+}
+
+async fn read_summary_file(path: &Path) -> Option<MediaSummary> {
+    let content = tokio::fs::read_to_string(path).await.ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Read image dimensions from the header, without decoding pixels
+fn image_dimensions(path: &Path) -> Result<Option<(u32, u32)>> {
+    if crate::models::svg::is_svg(path) {
+        return crate::models::svg::dimensions(path).map(Some);
+    }
+    // The embedded preview is smaller than the sensor, so RAW files have no size to show
+    if crate::models::raw::is_raw(path) {
+        return Ok(None);
+    }
+
+    let dimensions = image::ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_dimensions()?;
+    Ok(Some(dimensions))
+}
+
+/// Get or probe the metadata of an audio or video file
+pub async fn get_or_probe(
+    base_dir: &Path,
+    rel_path: &str,
+    ffmpeg_available: bool,
+) -> Result<Option<MediaMetadata>> {
+    if !ffmpeg_available {
+        return Ok(None);
+    }
+
+    let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path)?;
+
+    let (_, media_kind) = crate::models::media::detect(&abs_path);
+    if !matches!(
+        media_kind,
+        crate::models::media::MediaKind::Video | crate::models::media::MediaKind::Audio
+    ) {
+        return Ok(None);
+    }
+
+    let metadata = tokio::fs::metadata(&abs_path).await?;
+    let cache_path = cache_path(base_dir, rel_path, &metadata);
+
+    // Files ffprobe rejected leave a marker so they are not probed again until they change
+    let failed_marker = cache_path.with_extension("failed");
+
+    if let Some(cached) = read_cache_file(&cache_path).await {
+        return Ok(Some(cached));
+    }
+    if failed_marker.exists() {
+        return Ok(None);
+    }
+
+    let lock = PROBE_LOCKS
+        .entry(cache_path.to_string_lossy().to_string())
+        .or_insert_with(|| Arc::new(Mutex::new(())))
+        .clone();
+
+    let _guard = lock.lock().await;
+
+    if let Some(cached) = read_cache_file(&cache_path).await {
+        return Ok(Some(cached));
+    }
+    if failed_marker.exists() {
+        return Ok(None);
+    }
+
+    let result = {
+        let _permit = workers::acquire(Pool::Ffmpeg, Priority::Visible).await;
+        probe(&abs_path).await?
+    };
+
+    if let Some(cache_dir) = cache_path.parent() {
+        tokio::fs::create_dir_all(cache_dir).await?;
+    }
+    let media_metadata = match result {
+        Probe::Readable(media_metadata) => media_metadata,
+        Probe::Unreadable(e) => {
+            tracing::warn!("Failed to probe {}: {}", rel_path, e);
+            tokio::fs::write(&failed_marker, b"").await?;
+            return Ok(None);
+        }
+    };
+    let tmp = cache_path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, serde_json::to_string(&media_metadata)?).await?;
+    tokio::fs::rename(&tmp, &cache_path).await?;
+
+    Ok(Some(media_metadata))
+}
+
+async fn read_cache_file(path: &Path) -> Option<MediaMetadata> {
+    let content = tokio::fs::read_to_string(path).await.ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    format: ProbeFormat,
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+}
+
+#[derive(Deserialize, Default)]
+struct ProbeFormat {
+    #[serde(default)]
+    format_name: String,
+    format_long_name: Option<String>,
+    duration: Option<String>,
+    bit_rate: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_type: Option<String>,
+    #[serde(default)]
+    codec_name: String,
+    profile: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    bit_rate: Option<String>,
+    pix_fmt: Option<String>,
+    color_transfer: Option<String>,
+    #[serde(default)]
+    side_data_list: Vec<HashMap<String, serde_json::Value>>,
+    channels: Option<u32>,
+    channel_layout: Option<String>,
+    sample_rate: Option<String>,
+    #[serde(default)]
+    disposition: HashMap<String, i64>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+impl ProbeStream {
+    fn tag(&self, name: &str) -> Option<String> {
+        self.tags
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty() && value != "und")
+    }
+
+    fn hdr_formats(&self) -> Vec<String> {
+        let mut formats = Vec::new();
+        match self.color_transfer.as_deref() {
+            Some("smpte2084") => formats.push("HDR10".to_string()),
+            Some("arib-std-b67") => formats.push("HLG".to_string()),
+            _ => {}
+        }
+
+        let has_side_data = |name: &str| {
+            self.side_data_list.iter().any(|data| {
+                data.get("side_data_type")
+                    .and_then(|value| value.as_str())
+                    .is_some_and(|value| value.contains(name))
+            })
+        };
+        if has_side_data("HDR Dynamic Metadata SMPTE2094-40") {
+            formats.push("HDR10+".to_string());
+        }
+        if has_side_data("DOVI configuration record") {
+            formats.push("Dolby Vision".to_string());
+        }
+        formats
+    }
+}
+
+/// Parse ffprobe's fractional rates such as `30000/1001`
+fn parse_rate(value: &str) -> Option<f64> {
+    let (num, den) = value.split_once('/')?;
+    let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+    (num > 0.0 && den > 0.0).then_some(num / den)
+}
+
+/// What ffprobe made of a file it could run on
+enum Probe {
+    Readable(MediaMetadata),
+    /// ffprobe rejected the file or printed something unparsable
+    Unreadable(anyhow::Error),
+}
+
+/// Run ffprobe on a file and keep the fields shown to users.
+/// Errors are failures to run ffprobe at all.
+async fn probe(path: &Path) -> Result<Probe> {
+    let output = tokio::process::Command::new("ffprobe")
+        .arg("-v")
+        .arg("quiet")
+        .arg("-show_format")
+        .arg("-show_streams")
+        .arg("-of")
+        .arg("json")
+        .arg(path)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Ok(Probe::Unreadable(anyhow::anyhow!(
+            "ffprobe failed: {}",
+            stderr
+        )));
+    }
+
+    let probe: ProbeOutput = match serde_json::from_slice(&output.stdout) {
+        Ok(probe) => probe,
+        Err(e) => return Ok(Probe::Unreadable(e.into())),
+    };
+
+    let mut media_metadata = MediaMetadata {
+        container: probe.format.format_name,
+        container_name: probe.format.format_long_name,
+        duration: probe.format.duration.and_then(|d| d.parse().ok()),
+        bit_rate: probe.format.bit_rate.and_then(|b| b.parse().ok()),
+        video_streams: Vec::new(),
+        audio_streams: Vec::new(),
+        subtitle_streams: Vec::new(),
+    };
+
+    for stream in probe.streams {
+        match stream.codec_type.as_deref() {
+            // Embedded cover art shows up as a single-frame video stream
+            Some("video") if stream.disposition.get("attached_pic") != Some(&1) => {
+                media_metadata.video_streams.push(VideoStream {
+                    hdr: stream.hdr_formats(),
+                    codec: stream.codec_name,
+                    profile: stream.profile,
+                    width: stream.width.unwrap_or(0),
+                    height: stream.height.unwrap_or(0),
+                    frame_rate: stream.avg_frame_rate.as_deref().and_then(parse_rate),
+                    bit_rate: stream.bit_rate.and_then(|b| b.parse().ok()),
+                    pixel_format: stream.pix_fmt,
+                });
+            }
+            Some("audio") => {
+                media_metadata.audio_streams.push(AudioStream {
+                    language: stream.tag("language"),
+                    title: stream.tag("title"),
+                    codec: stream.codec_name,
+                    channels: stream.channels,
+                    channel_layout: stream.channel_layout,
+                    sample_rate: stream.sample_rate.and_then(|s| s.parse().ok()),
+                    bit_rate: stream.bit_rate.and_then(|b| b.parse().ok()),
+                });
+            }
+            Some("subtitle") => {
+                media_metadata.subtitle_streams.push(SubtitleStream {
+                    language: stream.tag("language"),
+                    title: stream.tag("title"),
+                    codec: stream.codec_name,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Probe::Readable(media_metadata))
+}
@@ -0,0 +1,312 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+lazy_static::lazy_static! {
+    /// Loaded from disk on first use
+    static ref STORE: Mutex<Option<ProgressStore>> = Mutex::new(None);
+}
+
+/// Set while a delayed save is scheduled
+static SAVE_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Mixed into generated client IDs so two IDs from the same instant differ
+static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Positions before this (in seconds) are not worth resuming
+const MIN_POSITION: f64 = 5.0;
+
+/// Playback this close to the end (in seconds) counts as finished
+const FINISHED_REMAINING: f64 = 30.0;
+
+/// Fraction of the duration after which playback counts as finished
+const FINISHED_FRACTION: f64 = 0.95;
+
+/// Oldest entries of a client are dropped past this
+const MAX_ENTRIES_PER_CLIENT: usize = 200;
+
+/// The least recently active client is dropped past this
+const MAX_CLIENTS: usize = 500;
+
+/// Lifetime of the client cookie, long enough for any break between two episodes;
+/// a client silent for longer cannot come back
+pub const CLIENT_MAX_AGE: Duration = Duration::from_secs(400 * 24 * 60 * 60);
+
+/// Updates arrive every few seconds per player, writes are batched
+const SAVE_DELAY: Duration = Duration::from_secs(5);
+
+/// Where a client stopped in an audio or video file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackProgress {
+    pub path: String,
+    /// Size and mtime (Unix seconds) of the file when the position was saved,
+    /// a replaced or re-encoded file starts over
+    pub size: u64,
+    #[serde(default)]
+    pub modified: u64,
+    /// Position and duration in seconds
+    pub position: f64,
+    pub duration: f64,
+    /// Unix seconds of the last update
+    pub updated: u64,
+}
+
+impl PlaybackProgress {
+    pub fn percent(&self) -> u32 {
+        if self.duration <= 0.0 {
+            return 0;
+        }
+        ((self.position / self.duration) * 100.0)
+            .clamp(0.0, 100.0)
+            .round() as u32
+    }
+}
+
+/// Positions of every client, keyed by client ID and then by relative path
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProgressStore {
+    clients: HashMap<String, HashMap<String, PlaybackProgress>>,
+}
+
+fn store_path(base_dir: &Path) -> PathBuf {
+    base_dir.join(".media-serve").join("progress.json")
+}
+
+/// Generate an anonymous client ID (32 hex characters)
+pub fn new_client_id() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    // RandomState is seeded from the OS for every process
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(ID_COUNTER.fetch_add(1, Ordering::Relaxed));
+
+    let mut input = Vec::with_capacity(32);
+    input.extend_from_slice(&nanos.to_le_bytes());
+    input.extend_from_slice(&hasher.finish().to_le_bytes());
+    input.extend_from_slice(&std::process::id().to_le_bytes());
+
+    blake3::hash(&input).to_hex()[..32].to_string()
+}
+
+/// Whether a cookie value looks like an ID we issued
+pub fn is_valid_client_id(value: &str) -> bool {
+    value.len() == 32 && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Run `f` on the store, loading it from disk the first time
+async fn with_store<R>(base_dir: &Path, f: impl FnOnce(&mut ProgressStore) -> R) -> R {
+    let mut guard = STORE.lock().await;
+    let store = match guard.as_mut() {
+        Some(store) => store,
+        None => {
+            let loaded = match tokio::fs::read_to_string(store_path(base_dir)).await {
+                Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                    tracing::warn!("Ignoring unreadable progress store: {}", e);
+                    ProgressStore::default()
+                }),
+                Err(_) => ProgressStore::default(),
+            };
+            guard.insert(loaded)
+        }
+    };
+    f(store)
+}
+
+/// Size and mtime of a playable file, `None` for anything that is not audio or video
+async fn playable_version(base_dir: &Path, rel_path: &str) -> Option<(u64, u64)> {
+    let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path).ok()?;
+    let (_, media_kind) = crate::models::media::detect(&abs_path);
+    if !matches!(
+        media_kind,
+        crate::models::media::MediaKind::Video | crate::models::media::MediaKind::Audio
+    ) {
+        return None;
+    }
+    let metadata = tokio::fs::metadata(&abs_path).await.ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Some((metadata.len(), modified))
+}
+
+/// Last update of any entry of a client
+fn last_active(entries: &HashMap<String, PlaybackProgress>) -> u64 {
+    entries
+        .values()
+        .map(|entry| entry.updated)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Drop clients past the cookie lifetime, then the least recently active ones
+/// until there is room for one more
+fn make_room_for_client(store: &mut ProgressStore) {
+    let expired_before = now().saturating_sub(CLIENT_MAX_AGE.as_secs());
+    store
+        .clients
+        .retain(|_, entries| last_active(entries) >= expired_before);
+
+    while store.clients.len() >= MAX_CLIENTS {
+        let oldest = store
+            .clients
+            .iter()
+            .min_by_key(|(_, entries)| last_active(entries))
+            .map(|(id, _)| id.clone());
+        match oldest {
+            Some(id) => store.clients.remove(&id),
+            None => break,
+        };
+    }
+}
+
+/// Record the position of a client in a file. Returns `false` if the file is not playable.
+pub async fn record(
+    base_dir: &Path,
+    client_id: &str,
+    rel_path: &str,
+    position: f64,
+    duration: f64,
+) -> Result<bool> {
+    let (size, modified) = match playable_version(base_dir, rel_path).await {
+        Some(version) => version,
+        None => return Ok(false),
+    };
+
+    let finished = duration > 0.0
+        && (position >= duration * FINISHED_FRACTION || duration - position <= FINISHED_REMAINING);
+
+    with_store(base_dir, |store| {
+        // Finished or barely started files have nothing to resume
+        if finished || position < MIN_POSITION {
+            if let Some(entries) = store.clients.get_mut(client_id) {
+                entries.remove(rel_path);
+                if entries.is_empty() {
+                    store.clients.remove(client_id);
+                }
+            }
+            return;
+        }
+
+        // Cookie-less clients get a new ID on every report, so the client count is bounded too
+        if !store.clients.contains_key(client_id) {
+            make_room_for_client(store);
+        }
+        let entries = store.clients.entry(client_id.to_string()).or_default();
+
+        entries.insert(
+            rel_path.to_string(),
+            PlaybackProgress {
+                path: rel_path.to_string(),
+                size,
+                modified,
+                position,
+                duration,
+                updated: now(),
+            },
+        );
+
+        if entries.len() > MAX_ENTRIES_PER_CLIENT {
+            if let Some(oldest) = entries
+                .values()
+                .min_by_key(|entry| entry.updated)
+                .map(|entry| entry.path.clone())
+            {
+                entries.remove(&oldest);
+            }
+        }
+    })
+    .await;
+
+    schedule_save(base_dir.to_path_buf());
+    Ok(true)
+}
+
+/// Saved position of a client in a file, unless the file changed since
+pub async fn get(base_dir: &Path, client_id: &str, rel_path: &str) -> Option<PlaybackProgress> {
+    let (size, modified) = playable_version(base_dir, rel_path).await?;
+    with_store(base_dir, |store| {
+        store
+            .clients
+            .get(client_id)?
+            .get(rel_path)
+            .filter(|entry| entry.size == size && entry.modified == modified)
+            .cloned()
+    })
+    .await
+}
+
+/// Most recently played unfinished files of a client that still exist unchanged
+pub async fn recent(base_dir: &Path, client_id: &str, limit: usize) -> Vec<PlaybackProgress> {
+    let mut entries: Vec<PlaybackProgress> = with_store(base_dir, |store| {
+        store
+            .clients
+            .get(client_id)
+            .map(|entries| entries.values().cloned().collect())
+            .unwrap_or_default()
+    })
+    .await;
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.updated));
+
+    let mut out = Vec::new();
+    for entry in entries {
+        if out.len() >= limit {
+            break;
+        }
+        if playable_version(base_dir, &entry.path).await == Some((entry.size, entry.modified)) {
+            out.push(entry);
+        }
+    }
+    out
+}
+
+/// Write the store to disk shortly, coalescing the updates that arrive meanwhile
+fn schedule_save(base_dir: PathBuf) {
+    if SAVE_PENDING.swap(true, Ordering::AcqRel) {
+        return;
+    }
+
+    tokio::spawn(async move {
+        tokio::time::sleep(SAVE_DELAY).await;
+        SAVE_PENDING.store(false, Ordering::Release);
+
+        // Writing under the lock keeps two saves from racing on the temp file
+        let guard = STORE.lock().await;
+        if let Some(store) = guard.as_ref() {
+            if let Err(e) = save(&base_dir, store).await {
+                tracing::warn!("Failed to save progress store: {}", e);
+            }
+        }
+    });
+}
+
+async fn save(base_dir: &Path, store: &ProgressStore) -> Result<()> {
+    let content = serde_json::to_string(store)?;
+    let path = store_path(base_dir);
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, content).await?;
+    tokio::fs::rename(&tmp, &path).await?;
+    Ok(())
+}
@@ -0,0 +1,363 @@
+use anyhow::Result;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder};
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Camera RAW extensions and the MIME type reported for each
+const RAW_FORMATS: &[(&str, &str)] = &[
+    ("cr2", "image/x-canon-cr2"),
+    ("nef", "image/x-nikon-nef"),
+    ("nrw", "image/x-nikon-nrw"),
+    ("arw", "image/x-sony-arw"),
+    ("dng", "image/x-adobe-dng"),
+    ("orf", "image/x-olympus-orf"),
+    ("rw2", "image/x-panasonic-rw2"),
+    ("pef", "image/x-pentax-pef"),
+    ("raf", "image/x-fuji-raf"),
+];
+
+/// Extensions of the developed copy a RAW file can be paired with
+const PAIR_EXTENSIONS: &[&str] = &["jpg", "jpeg"];
+
+/// Embedded previews larger than this are ignored rather than read into memory
+const MAX_PREVIEW_BYTES: u64 = 64 * 1024 * 1024;
+
+/// IFDs are walked at most this deep, which also stops offset loops in broken files
+const MAX_IFDS: usize = 32;
+
+/// TIFF tags used to locate embedded previews
+const TAG_SUBFILE_TYPE: u16 = 0x00FE;
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014A;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+/// Panasonic stores the full-size preview inline in this tag
+const TAG_RW2_JPEG: u16 = 0x002E;
+
+/// Offset of the JPEG pointer in a Fujifilm RAF header
+const RAF_MAGIC: &[u8] = b"FUJIFILMCCD-RAW";
+const RAF_JPEG_POINTER: u64 = 84;
+
+/// MIME type for a camera RAW file, by extension
+pub fn raw_mime(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    RAW_FORMATS
+        .iter()
+        .find(|(raw_ext, _)| *raw_ext == ext)
+        .map(|(_, mime)| *mime)
+}
+
+/// Whether the path is a camera RAW file
+pub fn is_raw(path: &Path) -> bool {
+    raw_mime(path).is_some()
+}
+
+/// Whether the path is a developed copy a RAW file can be paired with (`IMG_001.JPG` for `IMG_001.CR2`)
+pub fn is_pair_target(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| PAIR_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Decode the largest embedded preview of a RAW file, upright
+pub fn load_preview(path: &Path) -> Result<DynamicImage> {
+    let mut file = File::open(path)?;
+    let (mut candidates, orientation) = find_previews(&mut file)?;
+
+    // The largest one is the full-size preview
+    candidates.sort_by_key(|&(_, length)| std::cmp::Reverse(length));
+
+    for (offset, length) in candidates {
+        // Streams that cannot be inspected are still tried, decoding will tell
+        if matches!(is_lossless_jpeg(&mut file, offset), Ok(true)) {
+            continue;
+        }
+
+        let mut data = vec![0u8; length as usize];
+        file.seek(SeekFrom::Start(offset))?;
+        if file.read_exact(&mut data).is_err() || !data.starts_with(&[0xFF, 0xD8]) {
+            continue;
+        }
+
+        let mut decoder = match image::codecs::jpeg::JpegDecoder::new(Cursor::new(&data)) {
+            Ok(decoder) => decoder,
+            Err(e) => {
+                tracing::debug!("Skipping embedded preview at {}: {}", offset, e);
+                continue;
+            }
+        };
+        // The container orientation wins, the preview's own EXIF is a fallback
+        let orientation = orientation
+            .or_else(|| decoder.orientation().ok())
+            .unwrap_or(Orientation::NoTransforms);
+
+        match DynamicImage::from_decoder(decoder) {
+            Ok(mut img) => {
+                img.apply_orientation(orientation);
+                return Ok(img);
+            }
+            Err(e) => tracing::debug!("Failed to decode embedded preview at {}: {}", offset, e),
+        }
+    }
+
+    anyhow::bail!("no embedded preview found in {}", path.display())
+}
+
+/// Whether the JPEG stream at `offset` is lossless (raw sensor data rather than a preview)
+fn is_lossless_jpeg(file: &mut File, offset: u64) -> Result<bool> {
+    let mut head = vec![0u8; 64 * 1024];
+    file.seek(SeekFrom::Start(offset))?;
+    let read = file.read(&mut head)?;
+    let head = &head[..read];
+
+    if !head.starts_with(&[0xFF, 0xD8]) {
+        anyhow::bail!("not a JPEG stream");
+    }
+
+    // Walk the marker segments up to the frame header
+    let mut pos = 2;
+    while pos + 4 <= head.len() {
+        if head[pos] != 0xFF {
+            anyhow::bail!("corrupt JPEG marker");
+        }
+        let marker = head[pos + 1];
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            return Ok(matches!(marker, 0xC3 | 0xC7 | 0xCB | 0xCF));
+        }
+        let length = u16::from_be_bytes([head[pos + 2], head[pos + 3]]) as usize;
+        pos += 2 + length;
+    }
+
+    anyhow::bail!("no JPEG frame header found")
+}
+
+/// `(offset, length)` of an embedded JPEG stream
+type Candidate = (u64, u64);
+
+/// Collect every embedded JPEG that fits in the file, and the image orientation
+fn find_previews(file: &mut File) -> Result<(Vec<Candidate>, Option<Orientation>)> {
+    let file_len = file.metadata()?.len();
+    let mut magic = [0u8; 16];
+    file.read_exact(&mut magic)?;
+
+    let (mut candidates, orientation) = if magic.starts_with(RAF_MAGIC) {
+        file.seek(SeekFrom::Start(RAF_JPEG_POINTER))?;
+        let mut pointer = [0u8; 8];
+        file.read_exact(&mut pointer)?;
+        let offset = u32::from_be_bytes([pointer[0], pointer[1], pointer[2], pointer[3]]);
+        let length = u32::from_be_bytes([pointer[4], pointer[5], pointer[6], pointer[7]]);
+        (vec![(offset as u64, length as u64)], None)
+    } else {
+        find_tiff_previews(file, &magic)?
+    };
+
+    // Header values are untrusted: a broken or hostile file must not size the read buffer
+    candidates.retain(|(offset, length)| {
+        *length > 0 && *length <= MAX_PREVIEW_BYTES && offset.saturating_add(*length) <= file_len
+    });
+
+    Ok((candidates, orientation))
+}
+
+/// Walk the IFDs of a TIFF-based RAW file for embedded JPEGs and the orientation
+fn find_tiff_previews(
+    file: &mut File,
+    magic: &[u8],
+) -> Result<(Vec<Candidate>, Option<Orientation>)> {
+    let mut tiff = TiffReader::new(file, magic)?;
+    let mut candidates = Vec::new();
+    let mut orientation = None;
+
+    let mut queue = vec![tiff.first_ifd];
+    let mut visited = Vec::new();
+
+    while let Some(ifd_offset) = queue.pop() {
+        if ifd_offset == 0 || visited.contains(&ifd_offset) || visited.len() >= MAX_IFDS {
+            continue;
+        }
+        visited.push(ifd_offset);
+
+        let (entries, next) = match tiff.read_ifd(ifd_offset) {
+            Ok(ifd) => ifd,
+            Err(e) => {
+                tracing::debug!("Unreadable IFD at {}: {}", ifd_offset, e);
+                continue;
+            }
+        };
+        queue.push(next);
+
+        let find = |tag: u16| entries.iter().find(|entry| entry.tag == tag);
+
+        if orientation.is_none() {
+            orientation = find(TAG_ORIENTATION)
+                .and_then(|entry| tiff.values(entry).ok()?.first().copied())
+                .and_then(|value| Orientation::from_exif(value as u8));
+        }
+
+        if let Some(entry) = find(TAG_SUB_IFDS) {
+            queue.extend(tiff.values(entry).unwrap_or_default());
+        }
+
+        // Thumbnail/preview pointer used by most makers
+        if let (Some(offset), Some(length)) = (find(TAG_JPEG_OFFSET), find(TAG_JPEG_LENGTH)) {
+            if let (Ok(offset), Ok(length)) = (tiff.values(offset), tiff.values(length)) {
+                if let (Some(&offset), Some(&length)) = (offset.first(), length.first()) {
+                    candidates.push((offset, length));
+                }
+            }
+        }
+
+        // Single-strip JPEG images (Canon IFD0, DNG previews)
+        let is_jpeg_strip = find(TAG_COMPRESSION)
+            .and_then(|entry| tiff.values(entry).ok()?.first().copied())
+            .is_some_and(|compression| compression == 6 || compression == 7);
+        let is_reduced = find(TAG_SUBFILE_TYPE)
+            .and_then(|entry| tiff.values(entry).ok()?.first().copied())
+            .is_none_or(|subfile_type| subfile_type & 1 == 1);
+        if is_jpeg_strip && (is_reduced || ifd_offset == tiff.first_ifd) {
+            if let (Some(offsets), Some(counts)) =
+                (find(TAG_STRIP_OFFSETS), find(TAG_STRIP_BYTE_COUNTS))
+            {
+                if let (Ok(offsets), Ok(counts)) = (tiff.values(offsets), tiff.values(counts)) {
+                    if offsets.len() == 1 && counts.len() == 1 {
+                        candidates.push((offsets[0], counts[0]));
+                    }
+                }
+            }
+        }
+
+        if let Some(entry) = find(TAG_RW2_JPEG) {
+            candidates.push((entry.value_offset(), entry.count as u64));
+        }
+    }
+
+    Ok((candidates, orientation))
+}
+
+/// One IFD entry, values are read lazily
+struct IfdEntry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    /// The raw 4-byte value/offset field
+    value: [u8; 4],
+    little_endian: bool,
+}
+
+impl IfdEntry {
+    fn value_offset(&self) -> u64 {
+        read_u32(&self.value, self.little_endian) as u64
+    }
+}
+
+/// Minimal TIFF structure reader, enough to follow IFDs and read integer tags
+struct TiffReader<'a> {
+    file: &'a mut File,
+    little_endian: bool,
+    first_ifd: u64,
+}
+
+impl<'a> TiffReader<'a> {
+    fn new(file: &'a mut File, header: &[u8]) -> Result<Self> {
+        let little_endian = match &header[0..2] {
+            b"II" => true,
+            b"MM" => false,
+            _ => anyhow::bail!("not a TIFF-based RAW file"),
+        };
+
+        // 42 for TIFF, Olympus and Panasonic use their own magic numbers
+        let magic = read_u16(&header[2..4], little_endian);
+        if !matches!(magic, 42 | 0x4F52 | 0x5352 | 0x55) {
+            anyhow::bail!("unknown TIFF magic {:#x}", magic);
+        }
+
+        let first_ifd = read_u32(&header[4..8], little_endian) as u64;
+
+        Ok(Self {
+            file,
+            little_endian,
+            first_ifd,
+        })
+    }
+
+    /// Read all entries of an IFD and the offset of the next one
+    fn read_ifd(&mut self, offset: u64) -> Result<(Vec<IfdEntry>, u64)> {
+        self.file.seek(SeekFrom::Start(offset))?;
+        let mut count = [0u8; 2];
+        self.file.read_exact(&mut count)?;
+        let count = read_u16(&count, self.little_endian) as usize;
+
+        let mut raw = vec![0u8; count * 12 + 4];
+        self.file.read_exact(&mut raw)?;
+
+        let entries = raw
+            .chunks_exact(12)
+            .take(count)
+            .map(|chunk| IfdEntry {
+                tag: read_u16(&chunk[0..2], self.little_endian),
+                field_type: read_u16(&chunk[2..4], self.little_endian),
+                count: read_u32(&chunk[4..8], self.little_endian),
+                value: [chunk[8], chunk[9], chunk[10], chunk[11]],
+                little_endian: self.little_endian,
+            })
+            .collect();
+
+        let next = read_u32(&raw[count * 12..], self.little_endian) as u64;
+
+        Ok((entries, next))
+    }
+
+    /// Integer values of a SHORT/LONG/IFD entry
+    fn values(&mut self, entry: &IfdEntry) -> Result<Vec<u64>> {
+        let size = match entry.field_type {
+            3 => 2,
+            4 | 13 => 4,
+            _ => anyhow::bail!("unsupported field type {}", entry.field_type),
+        };
+
+        // Arrays of pointers are small; anything larger is not what we are looking for
+        let count = (entry.count as usize).min(64);
+        let total = size * count;
+
+        let data = if total <= 4 {
+            entry.value[..total].to_vec()
+        } else {
+            let mut data = vec![0u8; total];
+            self.file.seek(SeekFrom::Start(entry.value_offset()))?;
+            self.file.read_exact(&mut data)?;
+            data
+        };
+
+        Ok(data
+            .chunks_exact(size)
+            .map(|chunk| match size {
+                2 => read_u16(chunk, self.little_endian) as u64,
+                _ => read_u32(chunk, self.little_endian) as u64,
+            })
+            .collect())
+    }
+}
+
+fn read_u16(bytes: &[u8], little_endian: bool) -> u16 {
+    let bytes = [bytes[0], bytes[1]];
+    if little_endian {
+        u16::from_le_bytes(bytes)
+    } else {
+        u16::from_be_bytes(bytes)
+    }
+}
+
+fn read_u32(bytes: &[u8], little_endian: bool) -> u32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if little_endian {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::Result;
+use image::{DynamicImage, RgbaImage};
+use resvg::{tiny_skia, usvg};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Extensions rendered as vector images
+const SVG_EXTENSIONS: &[&str] = &["svg", "svgz"];
+
+/// Largest side of a rasterized SVG when no size is requested
+pub const DEFAULT_RENDER_SIZE: u32 = 1024;
+
+/// Upper bound for a single render, whatever the document claims
+const MAX_RENDER_SIZE: u32 = 4096;
+
+lazy_static::lazy_static! {
+    /// System fonts are loaded once, scanning them is slow
+    static ref FONTS: Arc<usvg::fontdb::Database> = {
+        let mut db = usvg::fontdb::Database::new();
+        db.load_system_fonts();
+        Arc::new(db)
+    };
+}
+
+/// Whether the path looks like an SVG document
+pub fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| SVG_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Whether the file is gzip-compressed SVGZ
+pub fn is_compressed(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("svgz"))
+        .unwrap_or(false)
+}
+
+/// Parser options that never touch the filesystem or network.
+/// Only `data:` images embedded in the document are resolved.
+fn options() -> usvg::Options<'static> {
+    usvg::Options {
+        fontdb: FONTS.clone(),
+        image_href_resolver: usvg::ImageHrefResolver {
+            resolve_data: usvg::ImageHrefResolver::default_data_resolver(),
+            resolve_string: Box::new(|_, _| None),
+        },
+        ..usvg::Options::default()
+    }
+}
+
+/// Intrinsic size of an SVG file, rounded to whole pixels
+pub fn dimensions(path: &Path) -> Result<(u32, u32)> {
+    let data = std::fs::read(path)?;
+    let size = usvg::Tree::from_data(&data, &options())?.size();
+    Ok((
+        size.width().round().max(1.0) as u32,
+        size.height().round().max(1.0) as u32,
+    ))
+}
+
+/// Rasterize an SVG file so its largest side is `size` pixels
+pub fn rasterize(path: &Path, size: u32) -> Result<DynamicImage> {
+    let data = std::fs::read(path)?;
+    rasterize_data(&data, size)
+}
+
+/// Rasterize SVG (or gzip-compressed SVGZ) data so its largest side is `size` pixels
+pub fn rasterize_data(data: &[u8], size: u32) -> Result<DynamicImage> {
+    let tree = usvg::Tree::from_data(data, &options())?;
+
+    let doc_size = tree.size();
+    let size = size.clamp(1, MAX_RENDER_SIZE) as f32;
+    let scale = size / doc_size.width().max(doc_size.height());
+    let width = (doc_size.width() * scale).round().max(1.0) as u32;
+    let height = (doc_size.height() * scale).round().max(1.0) as u32;
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| anyhow::anyhow!("invalid SVG size {}x{}", width, height))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    // tiny-skia works with premultiplied alpha, `image` expects straight alpha
+    let pixels: Vec<u8> = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+
+    let img = RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| anyhow::anyhow!("invalid SVG render buffer"))?;
+
+    Ok(DynamicImage::ImageRgba8(img))
+}
@@ -0,0 +1,548 @@
+use anyhow::Result;
+use dashmap::DashMap;
+use lofty::config::{ParseOptions, WriteOptions};
+use lofty::file::{AudioFile, FileType, TaggedFileExt};
+use lofty::flac::FlacFile;
+use lofty::id3::v2::{Frame, FrameId, TextInformationFrame};
+use lofty::mp4::{Atom, AtomData, AtomIdent, Mp4File};
+use lofty::mpeg::MpegFile;
+use lofty::ogg::{OggPictureStorage, OpusFile, VorbisFile};
+use lofty::picture::{Picture, PictureType};
+use lofty::tag::items::Timestamp;
+use lofty::tag::{Accessor, ItemKey, TagExt};
+use lofty::TextEncoding;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+type TagWriteLocks = Arc<DashMap<PathBuf, Arc<Mutex<()>>>>;
+
+lazy_static::lazy_static! {
+    static ref TAG_WRITE_LOCKS: TagWriteLocks = Arc::new(DashMap::new());
+}
+
+/// Numbers the temp files of cache writes and tag edits
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Extensions whose tags can be edited: ID3v2 in MP3, Vorbis comments in FLAC/Ogg/Opus, MP4 atoms
+const EDITABLE_EXTENSIONS: &[&str] = &["mp3", "flac", "ogg", "oga", "opus", "m4a", "m4b"];
+
+/// Tags read from ID3, Vorbis comments, APE or MP4 atoms
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AudioTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track: Option<u32>,
+    pub track_total: Option<u32>,
+    pub disc: Option<u32>,
+    pub disc_total: Option<u32>,
+    pub year: Option<u32>,
+    pub genre: Option<String>,
+    /// Duration in seconds, from the audio properties
+    pub duration: Option<f64>,
+}
+
+impl AudioTags {
+    /// Whether no descriptive tag is set (duration alone does not count)
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.artist.is_none()
+            && self.album.is_none()
+            && self.album_artist.is_none()
+            && self.track.is_none()
+            && self.year.is_none()
+            && self.genre.is_none()
+    }
+
+    pub fn formatted_duration(&self) -> Option<String> {
+        self.duration.map(crate::models::fs::format_duration)
+    }
+
+    /// `3/12` style position, or just the number without a total
+    pub fn track_label(&self) -> Option<String> {
+        position_label(self.track, self.track_total)
+    }
+
+    pub fn disc_label(&self) -> Option<String> {
+        position_label(self.disc, self.disc_total)
+    }
+
+    /// Whether any text tag contains `needle` (already lowercased)
+    pub fn matches(&self, needle: &str) -> bool {
+        [
+            &self.title,
+            &self.artist,
+            &self.album,
+            &self.album_artist,
+            &self.genre,
+        ]
+        .iter()
+        .filter_map(|value| value.as_deref())
+        .any(|value| value.to_lowercase().contains(needle))
+    }
+}
+
+fn position_label(number: Option<u32>, total: Option<u32>) -> Option<String> {
+    match (number?, total) {
+        (number, Some(total)) => Some(format!("{}/{}", number, total)),
+        (number, None) => Some(number.to_string()),
+    }
+}
+
+/// Whether the extension belongs to an audio format, without opening the file
+pub fn is_audio_path(path: &Path) -> bool {
+    mime_guess::from_path(path)
+        .first()
+        .is_some_and(|mime| mime.type_() == mime_guess::mime::AUDIO)
+}
+
+/// Read the tags of an audio file
+pub fn read(abs_path: &Path) -> Result<AudioTags> {
+    let tagged_file = lofty::read_from_path(abs_path)?;
+
+    let duration = tagged_file.properties().duration().as_secs_f64();
+    let mut tags = AudioTags {
+        duration: (duration > 0.0).then_some(duration),
+        ..AudioTags::default()
+    };
+
+    let tag = match tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag())
+    {
+        Some(tag) => tag,
+        None => return Ok(tags),
+    };
+
+    let text = |value: Option<std::borrow::Cow<'_, str>>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    tags.title = text(tag.title());
+    tags.artist = text(tag.artist());
+    tags.album = text(tag.album());
+    tags.album_artist = text(tag.get_string(ItemKey::AlbumArtist).map(Into::into));
+    tags.genre = text(tag.genre());
+    tags.track = tag.track().filter(|n| *n > 0);
+    tags.track_total = tag.track_total().filter(|n| *n > 0);
+    tags.disc = tag.disk().filter(|n| *n > 0);
+    tags.disc_total = tag.disk_total().filter(|n| *n > 0);
+    tags.year = tag
+        .date()
+        .map(|date| date.year as u32)
+        .filter(|year| *year > 0);
+
+    Ok(tags)
+}
+
+fn cache_path(base_dir: &Path, rel_path: &str, metadata: &std::fs::Metadata) -> PathBuf {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    base_dir.join(".media-serve").join("tags").join(format!(
+        "{}-{:x}-{:x}.json",
+        blake3::hash(rel_path.as_bytes()),
+        metadata.len(),
+        modified
+    ))
+}
+
+/// Tags of an audio file, read once per file version and cached as JSON.
+/// Returns `None` for files that are not audio or have unreadable tags.
+/// Blocking: call it from `spawn_blocking`.
+pub fn get_or_read(
+    base_dir: &Path,
+    rel_path: &str,
+    abs_path: &Path,
+    metadata: &std::fs::Metadata,
+) -> Option<AudioTags> {
+    if !is_audio_path(abs_path) {
+        return None;
+    }
+
+    // Unreadable tags are cached as `null`, so searches do not parse the file again
+    let cache_path = cache_path(base_dir, rel_path, metadata);
+    if let Ok(content) = std::fs::read(&cache_path) {
+        if let Ok(tags) = serde_json::from_slice(&content) {
+            return tags;
+        }
+    }
+
+    let tags = match read(abs_path) {
+        Ok(tags) => Some(tags),
+        Err(e) => {
+            tracing::debug!("Failed to read tags from {}: {}", abs_path.display(), e);
+            None
+        }
+    };
+
+    if let Err(e) = write_cache(&cache_path, tags.as_ref()) {
+        tracing::warn!("Failed to cache tags of {}: {}", rel_path, e);
+    }
+
+    tags
+}
+
+fn write_cache(cache_path: &Path, tags: Option<&AudioTags>) -> Result<()> {
+    if let Some(dir) = cache_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Searches may read the same file concurrently, each writer gets its own temp file
+    let tmp = cache_path.with_extension(format!(
+        "json.{}.tmp",
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&tmp, serde_json::to_vec(&tags)?)?;
+    std::fs::rename(&tmp, cache_path)?;
+    Ok(())
+}
+
+/// Tags of a file given its path relative to the base directory
+pub async fn get(base_dir: &Path, rel_path: &str) -> Result<Option<AudioTags>> {
+    let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path)?;
+    let metadata = tokio::fs::metadata(&abs_path).await?;
+    if !metadata.is_file() {
+        return Ok(None);
+    }
+
+    let base_dir = base_dir.to_path_buf();
+    let rel_path = rel_path.to_string();
+    Ok(
+        tokio::task::spawn_blocking(move || {
+            get_or_read(&base_dir, &rel_path, &abs_path, &metadata)
+        })
+        .await?,
+    )
+}
+
+/// Whether `write` supports the file's tag format
+pub fn is_editable(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EDITABLE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Token identifying a file version; the edit form sends it back so changes made since
+/// the form was loaded are not overwritten
+pub fn version(metadata: &std::fs::Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+
+    format!("{:x}-{:x}", metadata.len(), modified)
+}
+
+/// Outcome of a tag edit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagWrite {
+    Written,
+    /// The file changed since its version was read
+    Changed,
+    /// Not a tag format `write` knows
+    Unsupported,
+    /// The cover is not a JPEG, PNG, GIF, BMP or TIFF image
+    InvalidCover,
+}
+
+/// Replace the tags of an audio file, and its front cover when `cover` is given.
+/// Only fields that differ from the current tags are touched, other frames and atoms
+/// are kept as they are. The edit is made on a copy that replaces the file by rename,
+/// after checking again that the file still has `expected_version`.
+pub async fn write(
+    base_dir: &Path,
+    rel_path: &str,
+    expected_version: &str,
+    tags: AudioTags,
+    cover: Option<Vec<u8>>,
+) -> Result<TagWrite> {
+    let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path)?;
+    if !abs_path.is_file() || !is_editable(&abs_path) {
+        return Ok(TagWrite::Unsupported);
+    }
+
+    let cover = match cover {
+        Some(data) => match Picture::from_reader(&mut data.as_slice()) {
+            Ok(mut picture) => {
+                picture.set_pic_type(PictureType::CoverFront);
+                Some(picture)
+            }
+            Err(_) => return Ok(TagWrite::InvalidCover),
+        },
+        None => None,
+    };
+
+    // Edits of the same file from two forms run one after the other
+    let lock = TAG_WRITE_LOCKS
+        .entry(abs_path.clone())
+        .or_insert_with(|| Arc::new(Mutex::new(())))
+        .clone();
+    let _guard = lock.lock().await;
+
+    let expected_version = expected_version.to_string();
+    let outcome = {
+        let abs_path = abs_path.clone();
+        tokio::task::spawn_blocking(move || {
+            write_blocking(&abs_path, &expected_version, &tags, cover)
+        })
+        .await??
+    };
+
+    if outcome == TagWrite::Written {
+        // The cache key only has second precision and the size may not change
+        if let Ok(metadata) = std::fs::metadata(&abs_path) {
+            let _ = std::fs::remove_file(cache_path(base_dir, rel_path, &metadata));
+        }
+        crate::models::music::invalidate();
+        tracing::info!("Tags written to {}", rel_path);
+    }
+
+    Ok(outcome)
+}
+
+fn write_blocking(
+    abs_path: &Path,
+    expected_version: &str,
+    tags: &AudioTags,
+    cover: Option<Picture>,
+) -> Result<TagWrite> {
+    if version(&std::fs::metadata(abs_path)?) != expected_version {
+        return Ok(TagWrite::Changed);
+    }
+
+    let current = read(abs_path)?;
+
+    // Hidden temp file next to the original so the rename stays on one filesystem
+    let file_name = abs_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let tmp = abs_path.with_file_name(format!(
+        ".{}.{}.tags.tmp",
+        file_name,
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = (|| -> Result<TagWrite> {
+        std::fs::copy(abs_path, &tmp)?;
+        if !write_tags(&tmp, &current, tags, cover)? {
+            return Ok(TagWrite::Unsupported);
+        }
+        std::fs::File::open(&tmp)?.sync_all()?;
+
+        // Last check before replacing: the copy must not hide a change made meanwhile
+        if version(&std::fs::metadata(abs_path)?) != expected_version {
+            return Ok(TagWrite::Changed);
+        }
+        std::fs::rename(&tmp, abs_path)?;
+        Ok(TagWrite::Written)
+    })();
+
+    if !matches!(result, Ok(TagWrite::Written)) {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Apply the edit to the file at `path` in place. Returns `false` for unsupported formats.
+fn write_tags(
+    path: &Path,
+    current: &AudioTags,
+    tags: &AudioTags,
+    cover: Option<Picture>,
+) -> Result<bool> {
+    let parse_options = ParseOptions::new();
+    let write_options = WriteOptions::default();
+    let file_type = lofty::probe::Probe::open(path)?
+        .guess_file_type()?
+        .file_type();
+    let mut file = std::fs::File::open(path)?;
+    // `current` came from the primary tag, which is the one written below when it exists.
+    // A tag created here starts empty (older ID3v1/APE values are not carried over),
+    // so every field is written to it.
+    let blank = AudioTags::default();
+
+    match file_type {
+        Some(FileType::Mpeg) => {
+            let mpeg = MpegFile::read_from(&mut file, parse_options)?;
+            let current = if mpeg.id3v2().is_some() {
+                current
+            } else {
+                &blank
+            };
+            let mut tag = mpeg.id3v2().cloned().unwrap_or_default();
+            apply_common(&mut tag, current, tags);
+
+            if tags.album_artist != current.album_artist {
+                let id = FrameId::new("TPE2")?;
+                tag.remove(&id).for_each(drop);
+                if let Some(album_artist) = &tags.album_artist {
+                    tag.insert(Frame::Text(TextInformationFrame::new(
+                        id,
+                        TextEncoding::UTF8,
+                        album_artist.clone(),
+                    )));
+                }
+            }
+            if let Some(picture) = cover {
+                tag.remove_picture_type(PictureType::CoverFront);
+                tag.insert_picture(picture);
+            }
+
+            drop(file);
+            tag.save_to_path(path, write_options)?;
+        }
+        Some(FileType::Flac) => {
+            let mut flac = FlacFile::read_from(&mut file, parse_options)?;
+            let mut current = current;
+            if flac.vorbis_comments().is_none() {
+                flac.set_vorbis_comments(Default::default());
+                current = &blank;
+            }
+            if let Some(comments) = flac.vorbis_comments_mut() {
+                apply_common(comments, current, tags);
+                if tags.album_artist != current.album_artist {
+                    comments.remove("ALBUMARTIST").for_each(drop);
+                    if let Some(album_artist) = &tags.album_artist {
+                        comments.insert("ALBUMARTIST".to_string(), album_artist.clone());
+                    }
+                }
+            }
+            // FLAC keeps pictures in their own metadata blocks
+            if let Some(picture) = cover {
+                flac.remove_picture_type(PictureType::CoverFront);
+                flac.insert_picture(picture, None)?;
+            }
+
+            drop(file);
+            flac.save_to_path(path, write_options)?;
+        }
+        Some(file_type @ (FileType::Vorbis | FileType::Opus)) => {
+            let mut comments = if file_type == FileType::Vorbis {
+                VorbisFile::read_from(&mut file, parse_options)?
+                    .vorbis_comments()
+                    .clone()
+            } else {
+                OpusFile::read_from(&mut file, parse_options)?
+                    .vorbis_comments()
+                    .clone()
+            };
+            apply_common(&mut comments, current, tags);
+            if tags.album_artist != current.album_artist {
+                comments.remove("ALBUMARTIST").for_each(drop);
+                if let Some(album_artist) = &tags.album_artist {
+                    comments.insert("ALBUMARTIST".to_string(), album_artist.clone());
+                }
+            }
+            if let Some(picture) = cover {
+                comments.remove_picture_type(PictureType::CoverFront);
+                comments.insert_picture(picture, None)?;
+            }
+
+            drop(file);
+            comments.save_to_path(path, write_options)?;
+        }
+        Some(FileType::Mp4) => {
+            let mp4 = Mp4File::read_from(&mut file, parse_options)?;
+            let current = if mp4.ilst().is_some() {
+                current
+            } else {
+                &blank
+            };
+            let mut ilst = mp4.ilst().cloned().unwrap_or_default();
+            apply_common(&mut ilst, current, tags);
+
+            if tags.album_artist != current.album_artist {
+                let ident = AtomIdent::Fourcc(*b"aART");
+                ilst.remove(&ident).for_each(drop);
+                if let Some(album_artist) = &tags.album_artist {
+                    ilst.insert(Atom::new(ident, AtomData::UTF8(album_artist.clone())));
+                }
+            }
+            // MP4 covers have no picture type, the new one replaces them all
+            if let Some(picture) = cover {
+                ilst.remove_pictures();
+                ilst.insert_picture(picture);
+            }
+
+            drop(file);
+            ilst.save_to_path(path, write_options)?;
+        }
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+/// Set or remove the fields every tag format has, where the edit differs from `current`
+fn apply_common<T: Accessor>(tag: &mut T, current: &AudioTags, tags: &AudioTags) {
+    if tags.title != current.title {
+        match &tags.title {
+            Some(title) => tag.set_title(title.clone()),
+            None => tag.remove_title(),
+        }
+    }
+    if tags.artist != current.artist {
+        match &tags.artist {
+            Some(artist) => tag.set_artist(artist.clone()),
+            None => tag.remove_artist(),
+        }
+    }
+    if tags.album != current.album {
+        match &tags.album {
+            Some(album) => tag.set_album(album.clone()),
+            None => tag.remove_album(),
+        }
+    }
+    if tags.genre != current.genre {
+        match &tags.genre {
+            Some(genre) => tag.set_genre(genre.clone()),
+            None => tag.remove_genre(),
+        }
+    }
+    if tags.track != current.track {
+        match tags.track {
+            Some(track) => tag.set_track(track),
+            None => tag.remove_track(),
+        }
+    }
+    if tags.track_total != current.track_total {
+        match tags.track_total {
+            Some(total) => tag.set_track_total(total),
+            None => tag.remove_track_total(),
+        }
+    }
+    if tags.disc != current.disc {
+        match tags.disc {
+            Some(disc) => tag.set_disk(disc),
+            None => tag.remove_disk(),
+        }
+    }
+    if tags.disc_total != current.disc_total {
+        match tags.disc_total {
+            Some(total) => tag.set_disk_total(total),
+            None => tag.remove_disk_total(),
+        }
+    }
+    if tags.year != current.year {
+        match tags.year {
+            Some(year) => tag.set_date(Timestamp {
+                year: year as u16,
+                ..Timestamp::default()
+            }),
+            None => tag.remove_date(),
+        }
+    }
+}
@@ -1,65 +1,207 @@
 use anyhow::Result;
 use dashmap::DashMap;
+use futures::StreamExt;
 use image::GenericImageView;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 
+use crate::models::placeholders::{self, Placeholder};
+use crate::models::poster;
+use crate::models::workers::{self, Pool, Priority};
+
 type ThumbLocks = Arc<DashMap<String, Arc<Mutex<()>>>>;
+type CoverLookups = Arc<DashMap<PathBuf, (std::time::SystemTime, Option<PathBuf>)>>;
 
 lazy_static::lazy_static! {
     static ref THUMB_LOCKS: ThumbLocks = Arc::new(DashMap::new());
+    /// Cover file found in each folder, with the folder mtime it was looked up at
+    static ref COVER_LOOKUPS: CoverLookups = Arc::new(DashMap::new());
 }
 
-/// Get or build a thumbnail for the given file
+/// Number of tiles per side in a folder mosaic
+const MOSAIC_GRID: u32 = 2;
+
+/// Get or build a thumbnail for the given file or directory
 pub async fn get_or_build(
     base_dir: &Path,
     rel_path: &str,
     width: u32,
     ffmpeg_available: bool,
+    priority: Priority,
 ) -> Result<Option<PathBuf>> {
     let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path)?;
-    
+
+    let thumb_path = if abs_path.is_dir() {
+        get_or_build_folder(
+            base_dir,
+            rel_path,
+            &abs_path,
+            width,
+            ffmpeg_available,
+            priority,
+        )
+        .await?
+    } else {
+        get_or_build_file(
+            base_dir,
+            rel_path,
+            &abs_path,
+            width,
+            ffmpeg_available,
+            priority,
+        )
+        .await?
+    };
+
+    if let Some(thumb_path) = &thumb_path {
+        if let Err(e) = placeholders::ensure(thumb_path).await {
+            tracing::warn!("Failed to compute placeholder for {}: {}", rel_path, e);
+        }
+    }
+
+    Ok(thumb_path)
+}
+
+/// Placeholder of an already generated thumbnail, without building anything
+pub fn cached_placeholder(base_dir: &Path, rel_path: &str, width: u32) -> Option<Placeholder> {
+    let abs_path = base_dir.join(rel_path);
+    let cache_dir = base_dir.join(".media-serve").join("thumbs");
+
+    let cache_key = if abs_path.is_dir() {
+        let cover = folder_cover(&abs_path);
+        folder_cache_key(rel_path, &abs_path, cover.as_deref(), width).1
+    } else {
+        file_cache_key(rel_path, width)
+    };
+
+    placeholders::read(&cache_dir.join(format!("{}.jpg", cache_key)))
+}
+
+/// Cover file of a folder, scanning the folder again only when its mtime changes
+/// (adding, removing or renaming a cover does; rewriting one is caught by the cache key)
+fn folder_cover(abs_path: &Path) -> Option<PathBuf> {
+    let modified = std::fs::metadata(abs_path).and_then(|m| m.modified()).ok();
+
+    if let (Some(modified), Some(entry)) = (modified, COVER_LOOKUPS.get(abs_path)) {
+        if entry.0 == modified {
+            return entry.1.clone();
+        }
+    }
+
+    let cover = crate::models::cover_art::find_cover_file(abs_path);
+    if let Some(modified) = modified {
+        COVER_LOOKUPS.insert(abs_path.to_path_buf(), (modified, cover.clone()));
+    }
+    cover
+}
+
+/// Cache key of a file thumbnail
+fn file_cache_key(rel_path: &str, width: u32) -> String {
+    format!("{}-w{}", blake3::hash(rel_path.as_bytes()), width)
+}
+
+/// Cache key prefix and key of a folder preview, versioned by the directory
+/// and cover modification times
+fn folder_cache_key(
+    rel_path: &str,
+    abs_path: &Path,
+    cover: Option<&Path>,
+    width: u32,
+) -> (String, String) {
+    let version = [Some(abs_path), cover]
+        .iter()
+        .flatten()
+        .filter_map(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok())
+        .filter_map(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .max()
+        .unwrap_or(0);
+
+    let prefix = format!("{}-w{}-d", blake3::hash(rel_path.as_bytes()), width);
+    let key = format!("{}{}", prefix, version);
+    (prefix, key)
+}
+
+/// Get or build a thumbnail for a regular file
+async fn get_or_build_file(
+    base_dir: &Path,
+    rel_path: &str,
+    abs_path: &Path,
+    width: u32,
+    ffmpeg_available: bool,
+    priority: Priority,
+) -> Result<Option<PathBuf>> {
+    let abs_path = abs_path.to_path_buf();
+
     // Check media type
     let (_, media_kind) = crate::models::media::detect(&abs_path);
     if !media_kind.has_thumbnail() {
         return Ok(None);
     }
-    
+
     // Generate cache key and path
-    let cache_key = format!("{}-w{}", blake3::hash(rel_path.as_bytes()), width);
+    let cache_key = file_cache_key(rel_path, width);
     let cache_dir = base_dir.join(".media-serve").join("thumbs");
     let cache_path = cache_dir.join(format!("{}.jpg", cache_key));
-    
+
+    // A poster sidecar added after the thumbnail was built replaces it
+    let is_fresh = |cache_path: &Path| {
+        cache_path.exists()
+            && !(media_kind == crate::models::media::MediaKind::Video
+                && poster::sidecar_newer_than(&abs_path, cache_path))
+    };
+
     // Check if thumbnail already exists
-    if cache_path.exists() {
+    if is_fresh(&cache_path) {
         return Ok(Some(cache_path));
     }
-    
+
     // Get lock for this specific thumbnail
     let lock = THUMB_LOCKS
         .entry(cache_key.clone())
         .or_insert_with(|| Arc::new(Mutex::new(())))
         .clone();
-    
+
     let _guard = lock.lock().await;
-    
+
     // Check again after acquiring lock
-    if cache_path.exists() {
+    if is_fresh(&cache_path) {
         return Ok(Some(cache_path));
     }
-    
+
     // Create cache directory if needed
     tokio::fs::create_dir_all(&cache_dir).await?;
-    
+
     // Generate thumbnail based on media type
     match media_kind {
         crate::models::media::MediaKind::Image => {
+            let _permit = workers::acquire(Pool::Image, priority).await;
             generate_image_thumbnail(&abs_path, &cache_path, width).await?;
             Ok(Some(cache_path))
         }
-        crate::models::media::MediaKind::Video if ffmpeg_available => {
-            match generate_video_thumbnail(&abs_path, &cache_path, width).await {
+        crate::models::media::MediaKind::Video => {
+            // An explicit poster wins over anything picked from the video
+            if let Some(sidecar) = poster::find_sidecar(&abs_path) {
+                let _permit = workers::acquire(Pool::Image, priority).await;
+                generate_image_thumbnail(&sidecar, &cache_path, width).await?;
+                return Ok(Some(cache_path));
+            }
+
+            if !ffmpeg_available {
+                return Ok(None);
+            }
+
+            let _permit = workers::acquire(Pool::Ffmpeg, priority).await;
+            let time = match poster::pick_poster_time(&abs_path).await {
+                Ok(time) => time,
+                Err(e) => {
+                    tracing::debug!("Falling back to the default poster time: {}", e);
+                    1.0
+                }
+            };
+            match generate_video_thumbnail(&abs_path, &cache_path, width, time).await {
                 Ok(_) => Ok(Some(cache_path)),
                 Err(e) => {
                     tracing::warn!("Failed to generate video thumbnail: {}", e);
@@ -67,67 +209,394 @@ pub async fn get_or_build(
                 }
             }
         }
+        crate::models::media::MediaKind::Audio => {
+            {
+                let _permit = workers::acquire(Pool::Image, priority).await;
+                if generate_audio_thumbnail(&abs_path, &cache_path, width).await? {
+                    return Ok(Some(cache_path));
+                }
+            }
+
+            // Without cover art, the waveform stands in
+            let waveform = match crate::models::waveforms::get_or_build(
+                base_dir,
+                rel_path,
+                ffmpeg_available,
+                priority,
+            )
+            .await
+            {
+                Ok(Some(waveform)) => waveform,
+                Ok(None) => return Ok(None),
+                Err(e) => {
+                    tracing::warn!("Failed to build waveform thumbnail: {}", e);
+                    return Ok(None);
+                }
+            };
+
+            let _permit = workers::acquire(Pool::Image, priority).await;
+            let dest = cache_path.clone();
+            tokio::task::spawn_blocking(move || {
+                crate::models::waveforms::render(&waveform.peaks, width, (width * 2 / 3).max(1))
+                    .save_with_format(&dest, image::ImageFormat::Jpeg)
+            })
+            .await??;
+            Ok(Some(cache_path))
+        }
         _ => Ok(None),
     }
 }
 
+/// Get or build a folder preview: its cover image, or a mosaic of the first media items.
+/// The cache key includes the directory mtime so adding or removing files rebuilds it.
+async fn get_or_build_folder(
+    base_dir: &Path,
+    rel_path: &str,
+    abs_path: &Path,
+    width: u32,
+    ffmpeg_available: bool,
+    priority: Priority,
+) -> Result<Option<PathBuf>> {
+    let cover = folder_cover(abs_path);
+    let (key_prefix, cache_key) = folder_cache_key(rel_path, abs_path, cover.as_deref(), width);
+    let cache_dir = base_dir.join(".media-serve").join("thumbs");
+    let cache_path = cache_dir.join(format!("{}.jpg", cache_key));
+    // Marks a directory version without any media, so it is not scanned again
+    let empty_marker = cache_dir.join(format!("{}.none", cache_key));
+
+    if cache_path.exists() {
+        return Ok(Some(cache_path));
+    }
+    if empty_marker.exists() {
+        return Ok(None);
+    }
+
+    let lock = THUMB_LOCKS
+        .entry(cache_key.clone())
+        .or_insert_with(|| Arc::new(Mutex::new(())))
+        .clone();
+
+    let _guard = lock.lock().await;
+
+    if cache_path.exists() {
+        return Ok(Some(cache_path));
+    }
+    if empty_marker.exists() {
+        return Ok(None);
+    }
+
+    tokio::fs::create_dir_all(&cache_dir).await?;
+
+    let built = match cover {
+        Some(cover) => {
+            let _permit = workers::acquire(Pool::Image, priority).await;
+            let dest = cache_path.clone();
+            tokio::task::spawn_blocking(move || {
+                let img = load_image(&cover)?;
+                save_thumbnail(&img, &dest, width)
+            })
+            .await??;
+            true
+        }
+        None => {
+            // Thumbnails of the first media items, built outside the image pool slot
+            let items = crate::models::fs::list_dir(base_dir, rel_path, false)?;
+            let mut tiles = Vec::new();
+            for item in items.iter().filter(|item| !item.is_dir) {
+                if tiles.len() >= (MOSAIC_GRID * MOSAIC_GRID) as usize {
+                    break;
+                }
+                let item_abs = abs_path.join(&item.name);
+                let (_, kind) = crate::models::media::detect(&item_abs);
+                if !kind.has_thumbnail() {
+                    continue;
+                }
+                if let Ok(Some(thumb)) = get_or_build_file(
+                    base_dir,
+                    &item.rel_path,
+                    &item_abs,
+                    width,
+                    ffmpeg_available,
+                    priority,
+                )
+                .await
+                {
+                    if let Err(e) = placeholders::ensure(&thumb).await {
+                        tracing::warn!(
+                            "Failed to compute placeholder for {}: {}",
+                            item.rel_path,
+                            e
+                        );
+                    }
+                    tiles.push(thumb);
+                }
+            }
+
+            if tiles.is_empty() {
+                false
+            } else {
+                let _permit = workers::acquire(Pool::Image, priority).await;
+                let dest = cache_path.clone();
+                tokio::task::spawn_blocking(move || build_mosaic(&tiles, &dest, width)).await??;
+                true
+            }
+        }
+    };
+
+    let kept = if built {
+        cache_path.clone()
+    } else {
+        tokio::fs::write(&empty_marker, b"").await?;
+        empty_marker
+    };
+
+    // Drop previews and markers of older versions of this directory
+    if let Ok(mut entries) = tokio::fs::read_dir(&cache_dir).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with(&key_prefix) && entry.path() != kept {
+                let _ = tokio::fs::remove_file(entry.path()).await;
+            }
+        }
+    }
+
+    Ok(built.then_some(cache_path))
+}
+
+/// Compose up to four thumbnails into a square 2×2 mosaic
+fn build_mosaic(tiles: &[PathBuf], dest: &Path, width: u32) -> Result<()> {
+    let tile_size = (width / MOSAIC_GRID).max(1);
+    let side = tile_size * MOSAIC_GRID;
+    let mut canvas = image::RgbImage::from_pixel(side, side, image::Rgb([30, 41, 59]));
+
+    for (index, tile) in tiles.iter().enumerate() {
+        let img = image::open(tile)?;
+        let tile_img = img
+            .resize_to_fill(tile_size, tile_size, image::imageops::FilterType::Triangle)
+            .to_rgb8();
+        let x = (index as u32 % MOSAIC_GRID) * tile_size;
+        let y = (index as u32 / MOSAIC_GRID) * tile_size;
+        image::imageops::overlay(&mut canvas, &tile_img, x as i64, y as i64);
+    }
+
+    canvas.save_with_format(dest, image::ImageFormat::Jpeg)?;
+    Ok(())
+}
+
+/// Walk the whole tree and build missing thumbnails while the server is idle
+pub async fn pregenerate(
+    base_dir: PathBuf,
+    width: u32,
+    ffmpeg_available: bool,
+    show_hidden: bool,
+    concurrency: usize,
+) {
+    let walk_base = base_dir.clone();
+    let files = match tokio::task::spawn_blocking(move || {
+        collect_thumbnail_candidates(&walk_base, show_hidden)
+    })
+    .await
+    {
+        Ok(files) => files,
+        Err(e) => {
+            tracing::error!("Thumbnail crawler failed: {}", e);
+            return;
+        }
+    };
+
+    tracing::info!("Thumbnail crawler: {} candidate files", files.len());
+
+    let base_dir = &base_dir;
+    futures::stream::iter(files)
+        .for_each_concurrent(concurrency.max(1), |rel_path| async move {
+            // Back off while browsers are waiting for visible tiles
+            while !workers::is_idle(Duration::from_secs(2)) {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+
+            if let Err(e) = get_or_build(
+                base_dir,
+                &rel_path,
+                width,
+                ffmpeg_available,
+                Priority::Background,
+            )
+            .await
+            {
+                tracing::debug!("Crawler skipped {}: {}", rel_path, e);
+            }
+        })
+        .await;
+
+    tracing::info!("Thumbnail crawler finished");
+}
+
+/// List relative paths of every file that can have a thumbnail
+fn collect_thumbnail_candidates(base: &Path, show_hidden: bool) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut stack = vec![base.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+
+            // Never descend into our own caches
+            if name == ".media-serve" || name == ".video-previews" {
+                continue;
+            }
+            if !show_hidden && crate::models::fs::is_hidden(&name) {
+                continue;
+            }
+
+            let file_type = match entry.file_type() {
+                Ok(t) => t,
+                Err(_) => continue,
+            };
+
+            if file_type.is_dir() {
+                // Folders get a preview too
+                if let Ok(rel) = crate::models::fs::relative_from_base(base, &entry.path()) {
+                    out.push(rel);
+                }
+                stack.push(entry.path());
+            } else if file_type.is_file() {
+                let abs = entry.path();
+                let (_, kind) = crate::models::media::detect(&abs);
+                if kind.has_thumbnail() {
+                    if let Ok(rel) = crate::models::fs::relative_from_base(base, &abs) {
+                        out.push(rel);
+                    }
+                }
+            }
+        }
+    }
+
+    out
+}
+
 /// Generate thumbnail for an image file
 async fn generate_image_thumbnail(src: &Path, dest: &Path, width: u32) -> Result<()> {
     let src = src.to_path_buf();
     let dest = dest.to_path_buf();
-    
+
     tokio::task::spawn_blocking(move || {
-        let img = image::open(&src)?;
-        
-        // Calculate new dimensions maintaining aspect ratio
-        let (orig_width, orig_height) = img.dimensions();
-        let ratio = width as f32 / orig_width.max(orig_height) as f32;
-        
-        if ratio < 1.0 {
-            let new_width = (orig_width as f32 * ratio) as u32;
-            let new_height = (orig_height as f32 * ratio) as u32;
-            
-            let resized = img.resize(
-                new_width,
-                new_height,
-                image::imageops::FilterType::Lanczos3,
-            );
-            
-            resized.save_with_format(&dest, image::ImageFormat::Jpeg)?;
-        } else {
-            // Image is smaller than thumbnail size, just convert to JPEG
-            img.save_with_format(&dest, image::ImageFormat::Jpeg)?;
-        }
-        
-        Ok::<_, anyhow::Error>(())
+        let img = load_image_at(&src, width)?;
+        save_thumbnail(&img, &dest, width)
     })
     .await??;
-    
+
+    Ok(())
+}
+
+/// Generate thumbnail for an audio file from its cover art.
+/// Returns false when the file has no embedded or sidecar cover.
+async fn generate_audio_thumbnail(src: &Path, dest: &Path, width: u32) -> Result<bool> {
+    let src = src.to_path_buf();
+    let dest = dest.to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        let data = match crate::models::cover_art::find_audio_cover(&src) {
+            Some(data) => data,
+            None => return Ok(false),
+        };
+
+        let img = image::load_from_memory(&data)?;
+        save_thumbnail(&img, &dest, width)?;
+
+        Ok::<_, anyhow::Error>(true)
+    })
+    .await?
+}
+
+/// Decode an image file, detecting the format from its content
+pub fn load_image(path: &Path) -> Result<image::DynamicImage> {
+    load_image_at(path, crate::models::svg::DEFAULT_RENDER_SIZE)
+}
+
+/// Decode an image file; vector images are rasterized with `size` as their largest side
+/// and camera RAW files use their embedded preview
+pub fn load_image_at(path: &Path, size: u32) -> Result<image::DynamicImage> {
+    if crate::models::svg::is_svg(path) {
+        return crate::models::svg::rasterize(path, size);
+    }
+
+    if crate::models::raw::is_raw(path) {
+        return crate::models::raw::load_preview(path);
+    }
+
+    let img = image::ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()?;
+    Ok(img)
+}
+
+/// Drop the alpha channel by compositing over white, so transparent areas don't turn black
+pub fn flatten(img: &image::DynamicImage) -> image::RgbImage {
+    if !img.color().has_alpha() {
+        return img.to_rgb8();
+    }
+
+    let rgba = img.to_rgba8();
+    image::RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// Resize an image to fit `width` (largest side) and save it as JPEG
+fn save_thumbnail(img: &image::DynamicImage, dest: &Path, width: u32) -> Result<()> {
+    // Calculate new dimensions maintaining aspect ratio
+    let (orig_width, orig_height) = img.dimensions();
+    let ratio = width as f32 / orig_width.max(orig_height) as f32;
+
+    let resized = if ratio < 1.0 {
+        let new_width = (orig_width as f32 * ratio) as u32;
+        let new_height = (orig_height as f32 * ratio) as u32;
+
+        img.resize(new_width, new_height, image::imageops::FilterType::Lanczos3)
+    } else {
+        // Image is smaller than thumbnail size, just convert to JPEG
+        img.clone()
+    };
+
+    // JPEG has no alpha channel
+    image::DynamicImage::ImageRgb8(flatten(&resized))
+        .save_with_format(dest, image::ImageFormat::Jpeg)?;
+
     Ok(())
 }
 
 /// Generate thumbnail for a video file using ffmpeg
-async fn generate_video_thumbnail(src: &Path, dest: &Path, width: u32) -> Result<()> {
+async fn generate_video_thumbnail(src: &Path, dest: &Path, width: u32, time: f64) -> Result<()> {
     let output = tokio::process::Command::new("ffmpeg")
         .arg("-y")
         .arg("-ss")
-        .arg("1")
+        .arg(format!("{:.3}", time))
         .arg("-i")
         .arg(src)
         .arg("-frames:v")
         .arg("1")
         .arg("-vf")
-        .arg(format!("scale='min({},iw)':'min({},ih)':force_original_aspect_ratio=decrease", width, width))
+        .arg(format!(
+            "scale='min({},iw)':'min({},ih)':force_original_aspect_ratio=decrease",
+            width, width
+        ))
         .arg("-q:v")
         .arg("2")
         .arg(dest)
         .output()
         .await?;
-    
+
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         anyhow::bail!("ffmpeg failed: {}", stderr);
     }
-    
+
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,90 +1,267 @@
 use anyhow::Result;
+use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
 
-type PreviewLocks = Arc<DashMap<String, Arc<Mutex<()>>>>;
+use crate::models::workers::{self, Pool, Priority};
+
+type PreviewJobs = Arc<DashMap<String, Arc<PreviewJob>>>;
+type PreviewLocks = Arc<DashMap<String, Arc<tokio::sync::Mutex<()>>>>;
+type ChapterLocks = Arc<DashMap<String, Arc<tokio::sync::Mutex<()>>>>;
 
 lazy_static::lazy_static! {
+    static ref PREVIEW_JOBS: PreviewJobs = Arc::new(DashMap::new());
     static ref PREVIEW_LOCKS: PreviewLocks = Arc::new(DashMap::new());
+    static ref CHAPTER_LOCKS: ChapterLocks = Arc::new(DashMap::new());
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A job with no SSE subscriber and no poll for this long is cancelled
+const UNWATCHED_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a running job checks whether anyone still watches it
+const WATCH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Intervals at least this long (in seconds) decode keyframes only
+const KEYFRAME_ONLY_INTERVAL: f64 = 10.0;
+
+/// Size of each preview frame
+const TILE_WIDTH: u32 = 160;
+const TILE_HEIGHT: u32 = 90;
+
+/// Frames per sprite sheet row and maximum rows per sheet
+const SPRITE_COLUMNS: u32 = 10;
+const SPRITE_ROWS: u32 = 5;
+
+/// WebVTT thumbnails track stored next to the sprite sheets
+const VTT_FILENAME: &str = "thumbnails.vtt";
+
+/// At most this many scene cuts are kept
+const MAX_SCENES: usize = 60;
+
+/// Chapter thumbnails are taken this far into the chapter, skipping title fades
+const CHAPTER_THUMB_OFFSET: f64 = 3.0;
+
+/// Scene scores are computed on a downscaled copy, full frames are not needed to spot a cut
+const SCENE_ANALYSIS_WIDTH: u32 = 320;
+
+/// Bytes of ffmpeg's stderr kept for error messages
+const STDERR_TAIL_BYTES: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VideoPreviewInfo {
     pub duration: f64,
     pub interval: f64,
     pub thumbnails: Vec<VideoThumbnail>,
     /// Número esperado de miniaturas (estimativa) — útil para progresso
     pub expected_count: usize,
+    /// Folhas de sprites, montadas quando todas as miniaturas estão prontas
+    #[serde(default)]
+    pub sprites: Vec<SpriteSheet>,
+    /// Geração terminada (caches antigos só eram gravados ao final)
+    #[serde(default = "default_complete")]
+    pub complete: bool,
+    /// Miniaturas nos cortes de cena, quando a detecção está ligada
+    #[serde(default)]
+    pub scenes: Vec<VideoScene>,
+    /// Limiar usado na detecção de cenas (`None` se desligada)
+    #[serde(default)]
+    pub scene_threshold: Option<f64>,
+    /// Capítulos do contêiner (`None` em caches que ainda não foram sondados)
+    #[serde(default)]
+    pub chapters: Option<Vec<VideoChapter>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+fn default_complete() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VideoThumbnail {
     pub time: f64,
     pub filename: String,
+    /// Position of this frame inside a sprite sheet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sprite: Option<SpriteTile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoScene {
+    /// Start of the scene in seconds
+    pub time: f64,
+    pub filename: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoChapter {
+    pub title: String,
+    /// Start and end in seconds
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpriteSheet {
+    pub filename: String,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpriteTile {
+    /// Index into `VideoPreviewInfo::sprites`
+    pub sheet: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A preview generation running in the background
+pub struct PreviewJob {
+    /// Latest progress, also the channel SSE streams subscribe to
+    progress: watch::Sender<VideoPreviewInfo>,
+    /// Last time a client polled the JSON progress
+    last_seen: Mutex<Instant>,
+}
+
+impl PreviewJob {
+    fn new(info: VideoPreviewInfo) -> Self {
+        let (progress, _) = watch::channel(info);
+        Self {
+            progress,
+            last_seen: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn touch(&self) {
+        *self.last_seen.lock().unwrap() = Instant::now();
+    }
+
+    /// Whether an SSE stream is open or a client polled recently
+    fn is_watched(&self) -> bool {
+        self.progress.receiver_count() > 0
+            || self.last_seen.lock().unwrap().elapsed() < UNWATCHED_TIMEOUT
+    }
+
+    fn publish(&self, info: &VideoPreviewInfo) {
+        self.progress.send_replace(info.clone());
+    }
 }
 
-/// Get or build video preview thumbnails
-pub async fn get_or_build_previews(
+/// Paths of the preview cache of a video
+struct PreviewPaths {
+    abs_path: PathBuf,
+    cache_key: String,
+    cache_dir: PathBuf,
+    info_file: PathBuf,
+}
+
+impl PreviewPaths {
+    fn resolve(base_dir: &Path, rel_path: &str) -> Result<Option<Self>> {
+        let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path)?;
+
+        // Check if it's a video file
+        let (_, media_kind) = crate::models::media::detect(&abs_path);
+        if !matches!(media_kind, crate::models::media::MediaKind::Video) {
+            return Ok(None);
+        }
+
+        // Generate cache key and directory
+        let cache_key = blake3::hash(rel_path.as_bytes()).to_string();
+        let cache_dir = abs_path
+            .parent()
+            .ok_or_else(|| anyhow::anyhow!("Não foi possível obter o diretório pai"))?
+            .join(".video-previews")
+            .join(&cache_key);
+        let info_file = cache_dir.join("info.json");
+
+        Ok(Some(Self {
+            abs_path,
+            cache_key,
+            cache_dir,
+            info_file,
+        }))
+    }
+}
+
+impl VideoPreviewInfo {
+    /// Whether every expected frame has been extracted
+    fn is_finished(&self) -> bool {
+        !self.thumbnails.is_empty() && self.thumbnails.len() >= self.expected_count
+    }
+}
+
+/// Get the preview info of a video, starting a background job if it isn't generated yet.
+/// While the job runs, the returned info reflects its progress.
+/// With `scene_threshold`, thumbnails are also taken at scene cuts.
+pub async fn get_or_start_previews(
     base_dir: &Path,
     rel_path: &str,
     ffmpeg_available: bool,
+    scene_threshold: Option<f64>,
 ) -> Result<Option<VideoPreviewInfo>> {
     if !ffmpeg_available {
         return Ok(None);
     }
 
-    let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path)?;
+    let paths = match PreviewPaths::resolve(base_dir, rel_path)? {
+        Some(paths) => paths,
+        None => return Ok(None),
+    };
 
-    // Check if it's a video file
-    let (_, media_kind) = crate::models::media::detect(&abs_path);
-    if !matches!(media_kind, crate::models::media::MediaKind::Video) {
-        return Ok(None);
+    // A running job has the freshest progress
+    if let Some(job) = PREVIEW_JOBS.get(&paths.cache_key).map(|job| job.clone()) {
+        job.touch();
+        return Ok(Some(job.progress.borrow().clone()));
     }
 
-    // Generate cache key and directory
-    let cache_key = blake3::hash(rel_path.as_bytes()).to_string();
-    let cache_dir = abs_path
-        .parent()
-        .ok_or_else(|| anyhow::anyhow!("Não foi possível obter o diretório pai"))?
-        .join(".video-previews")
-        .join(&cache_key);
-
-    let info_file = cache_dir.join("info.json");
-
-    // Check if preview info already exists
-    if info_file.exists() {
-        if let Ok(content) = tokio::fs::read_to_string(&info_file).await {
-            if let Ok(info) = serde_json::from_str::<VideoPreviewInfo>(&content) {
-                return Ok(Some(info));
-            }
-        }
-    }
-
-    // Get lock for this specific video
+    // Cleaning up a stale cache and starting the job happen under one lock,
+    // so a second first request cannot wipe the directory a new job writes into
     let lock = PREVIEW_LOCKS
-        .entry(cache_key.clone())
-        .or_insert_with(|| Arc::new(Mutex::new(())))
+        .entry(paths.cache_key.clone())
+        .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(())))
         .clone();
-
     let _guard = lock.lock().await;
 
-    // Check again after acquiring lock
-    if info_file.exists() {
-        if let Ok(content) = tokio::fs::read_to_string(&info_file).await {
-            if let Ok(info) = serde_json::from_str::<VideoPreviewInfo>(&content) {
-                return Ok(Some(info));
+    if let Some(job) = PREVIEW_JOBS.get(&paths.cache_key).map(|job| job.clone()) {
+        job.touch();
+        return Ok(Some(job.progress.borrow().clone()));
+    }
+
+    // Incomplete info without a job was interrupted (e.g. by a restart) and is rebuilt,
+    // as are caches built without the scene detection that is now enabled
+    if let Some(mut info) = read_info(&paths.info_file).await {
+        let scenes_match = scene_threshold.is_none() || info.scene_threshold == scene_threshold;
+        if info.complete && scenes_match {
+            // Caches from before sprite sheets existed get them on first use
+            if info.sprites.is_empty() && info.is_finished() {
+                upgrade_sprites(&paths, rel_path, &mut info).await?;
             }
+            // Same for chapters
+            if info.chapters.is_none() {
+                info.chapters = Some(probe_chapters(&paths.abs_path).await);
+                if let Err(e) = write_info(&paths.info_file, &info).await {
+                    tracing::warn!("Falha ao gravar capítulos de {}: {}", rel_path, e);
+                }
+            }
+            return Ok(Some(info));
+        }
+
+        if let Err(e) = tokio::fs::remove_dir_all(&paths.cache_dir).await {
+            tracing::warn!("Falha ao limpar prévias antigas: {}", e);
         }
     }
 
     // Create cache directory
-    tokio::fs::create_dir_all(&cache_dir).await?;
+    tokio::fs::create_dir_all(&paths.cache_dir).await?;
 
     // Get video duration
-    let duration = get_video_duration(&abs_path).await?;
+    let duration = get_video_duration(&paths.abs_path).await?;
+    let chapters = probe_chapters(&paths.abs_path).await;
 
     // Calculate interval (aim for ~100 thumbnails max)
     let max_thumbnails = 100;
@@ -93,96 +270,682 @@ pub async fn get_or_build_previews(
     // Estimate expected count
     let expected_count = ((duration / interval).ceil() as usize).max(1);
 
-    // Prepare preview info and write initial info.json so frontend can poll progress
-    let mut preview_info = VideoPreviewInfo {
+    // Prepare preview info
+    let preview_info = VideoPreviewInfo {
         duration,
         interval,
         thumbnails: Vec::new(),
         expected_count,
+        sprites: Vec::new(),
+        complete: false,
+        scenes: Vec::new(),
+        scene_threshold,
+        chapters: Some(chapters),
+    };
+
+    let job = Arc::new(PreviewJob::new(preview_info.clone()));
+    PREVIEW_JOBS.insert(paths.cache_key.clone(), job.clone());
+
+    // Spawned right away: the job owns the entry and removes it however it ends
+    let initial = preview_info.clone();
+    let rel_path = rel_path.to_string();
+    tokio::spawn(run_preview_job(paths, rel_path, job, preview_info));
+
+    Ok(Some(initial))
+}
+
+/// Subscribe to the progress of a running job, if any
+pub fn subscribe(base_dir: &Path, rel_path: &str) -> Option<watch::Receiver<VideoPreviewInfo>> {
+    let paths = PreviewPaths::resolve(base_dir, rel_path).ok()??;
+    let job = PREVIEW_JOBS.get(&paths.cache_key)?;
+    Some(job.progress.subscribe())
+}
+
+/// Generate the frames and sprites of a video, then drop the job
+async fn run_preview_job(
+    paths: PreviewPaths,
+    rel_path: String,
+    job: Arc<PreviewJob>,
+    mut preview_info: VideoPreviewInfo,
+) {
+    // Write initial info.json so the frontend can poll progress
+    if let Err(e) = write_info(&paths.info_file, &preview_info).await {
+        tracing::warn!("Falha ao gravar info de prévias de {}: {}", rel_path, e);
+        PREVIEW_JOBS.remove(&paths.cache_key);
+        return;
+    }
+
+    // Each pass decodes the whole video, so it waits for an ffmpeg slot like other jobs
+    let permit = workers::acquire(Pool::Ffmpeg, Priority::Background).await;
+    // Generate all thumbnails in one pass, updating info.json as frames come out
+    let result = generate_preview_thumbnails(&paths, &job, &mut preview_info).await;
+    drop(permit);
+    let mut finished = match result {
+        Ok(finished) => finished,
+        Err(e) => {
+            tracing::warn!("Falha ao gerar miniaturas de {}: {}", rel_path, e);
+            true
+        }
+    };
+
+    // Scene cuts need every frame decoded, so they get their own pass
+    if let (true, Some(threshold)) = (finished, preview_info.scene_threshold) {
+        let permit = workers::acquire(Pool::Ffmpeg, Priority::Background).await;
+        let result = generate_scene_thumbnails(&paths, &job, &mut preview_info, threshold).await;
+        drop(permit);
+        finished = match result {
+            Ok(finished) => finished,
+            Err(e) => {
+                tracing::warn!("Falha ao detectar cenas de {}: {}", rel_path, e);
+                true
+            }
+        };
+    }
+
+    if !finished {
+        tracing::info!(
+            "Geração de prévias cancelada, ninguém acompanhando: {}",
+            rel_path
+        );
+        if let Err(e) = tokio::fs::remove_dir_all(&paths.cache_dir).await {
+            tracing::warn!("Falha ao limpar prévias canceladas: {}", e);
+        }
+        PREVIEW_JOBS.remove(&paths.cache_key);
+        return;
+    }
+
+    // The real frame count may differ from the estimate; settle it so polling stops
+    preview_info.expected_count = preview_info.thumbnails.len();
+    preview_info.complete = true;
+
+    let result = if preview_info.is_finished() {
+        build_sprites(
+            &paths.cache_dir,
+            &paths.info_file,
+            &rel_path,
+            &mut preview_info,
+        )
+        .await
+    } else {
+        write_info(&paths.info_file, &preview_info).await
     };
+    if let Err(e) = result {
+        tracing::warn!("Falha ao finalizar prévias de {}: {}", rel_path, e);
+    }
+
+    job.publish(&preview_info);
+    PREVIEW_JOBS.remove(&paths.cache_key);
+}
+
+/// Build sprites for a complete cache that predates them, unless a job owns the video
+async fn upgrade_sprites(
+    paths: &PreviewPaths,
+    rel_path: &str,
+    info: &mut VideoPreviewInfo,
+) -> Result<()> {
+    match PREVIEW_JOBS.entry(paths.cache_key.clone()) {
+        Entry::Occupied(_) => return Ok(()),
+        Entry::Vacant(entry) => {
+            entry.insert(Arc::new(PreviewJob::new(info.clone())));
+        }
+    }
+
+    let result = build_sprites(&paths.cache_dir, &paths.info_file, rel_path, info).await;
+    PREVIEW_JOBS.remove(&paths.cache_key);
+    result
+}
+
+/// Read a stored info.json
+async fn read_info(info_file: &Path) -> Option<VideoPreviewInfo> {
+    let content = tokio::fs::read_to_string(info_file).await.ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Tile the extracted frames into sprite sheets and write a WebVTT thumbnails track
+async fn build_sprites(
+    cache_dir: &Path,
+    info_file: &Path,
+    rel_path: &str,
+    preview_info: &mut VideoPreviewInfo,
+) -> Result<()> {
+    let frames: Vec<PathBuf> = preview_info
+        .thumbnails
+        .iter()
+        .map(|thumb| cache_dir.join(&thumb.filename))
+        .collect();
+    let dir = cache_dir.to_path_buf();
+
+    let sheets = tokio::task::spawn_blocking(move || write_sprite_sheets(&dir, &frames)).await??;
+
+    let per_sheet = (SPRITE_COLUMNS * SPRITE_ROWS) as usize;
+    for (index, thumb) in preview_info.thumbnails.iter_mut().enumerate() {
+        let position = (index % per_sheet) as u32;
+        thumb.sprite = Some(SpriteTile {
+            sheet: index / per_sheet,
+            x: position % SPRITE_COLUMNS * TILE_WIDTH,
+            y: position / SPRITE_COLUMNS * TILE_HEIGHT,
+            width: TILE_WIDTH,
+            height: TILE_HEIGHT,
+        });
+    }
+    preview_info.sprites = sheets;
+
+    let vtt = build_vtt(preview_info, &crate::models::fs::url_encode_path(rel_path));
+    let vtt_path = cache_dir.join(VTT_FILENAME);
+    let tmp = vtt_path.with_extension("vtt.tmp");
+    tokio::fs::write(&tmp, vtt).await?;
+    tokio::fs::rename(&tmp, &vtt_path).await?;
+
+    write_info(info_file, preview_info).await
+}
+
+/// Compose frames into JPEG sheets of up to `SPRITE_COLUMNS` x `SPRITE_ROWS` tiles
+fn write_sprite_sheets(cache_dir: &Path, frames: &[PathBuf]) -> Result<Vec<SpriteSheet>> {
+    let per_sheet = (SPRITE_COLUMNS * SPRITE_ROWS) as usize;
+    let mut sheets = Vec::new();
+
+    for (sheet_index, chunk) in frames.chunks(per_sheet).enumerate() {
+        let columns = SPRITE_COLUMNS.min(chunk.len() as u32);
+        let rows = (chunk.len() as u32).div_ceil(SPRITE_COLUMNS);
+        let mut sheet = image::RgbImage::new(columns * TILE_WIDTH, rows * TILE_HEIGHT);
+
+        for (position, frame) in chunk.iter().enumerate() {
+            let position = position as u32;
+            let tile = image::open(frame)?.resize_exact(
+                TILE_WIDTH,
+                TILE_HEIGHT,
+                image::imageops::FilterType::Triangle,
+            );
+            image::imageops::replace(
+                &mut sheet,
+                &tile.to_rgb8(),
+                (position % SPRITE_COLUMNS * TILE_WIDTH) as i64,
+                (position / SPRITE_COLUMNS * TILE_HEIGHT) as i64,
+            );
+        }
+
+        let filename = format!("sprite_{}.jpg", sheet_index);
+        let tmp = cache_dir.join(format!("sprite_{}.tmp.jpg", sheet_index));
+        sheet.save_with_format(&tmp, image::ImageFormat::Jpeg)?;
+        std::fs::rename(&tmp, cache_dir.join(&filename))?;
+
+        sheets.push(SpriteSheet {
+            filename,
+            columns,
+            rows,
+        });
+    }
+
+    Ok(sheets)
+}
+
+/// WebVTT track pointing each interval at its tile (`#xywh=`) in the sprite sheets
+fn build_vtt(preview_info: &VideoPreviewInfo, encoded_path: &str) -> String {
+    let mut vtt = String::from("WEBVTT\n");
+
+    for (index, thumb) in preview_info.thumbnails.iter().enumerate() {
+        let tile = match &thumb.sprite {
+            Some(tile) => tile,
+            None => continue,
+        };
+        let end = preview_info
+            .thumbnails
+            .get(index + 1)
+            .map(|next| next.time)
+            .unwrap_or(preview_info.duration)
+            .max(thumb.time);
+
+        vtt.push_str(&format!(
+            "\n{} --> {}\n/video-previews/{}?sprite={}#xywh={},{},{},{}\n",
+            format_vtt_time(thumb.time),
+            format_vtt_time(end),
+            encoded_path,
+            tile.sheet,
+            tile.x,
+            tile.y,
+            tile.width,
+            tile.height
+        ));
+    }
+
+    vtt
+}
+
+/// Format seconds as a WebVTT timestamp (`HH:MM:SS.mmm`)
+fn format_vtt_time(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Write info.json atomically (temp file then rename)
+async fn write_info(info_file: &Path, info: &VideoPreviewInfo) -> Result<()> {
+    let info_json = serde_json::to_string_pretty(info)?;
+    let tmp = info_file.with_extension("json.tmp");
+    tokio::fs::write(&tmp, info_json).await?;
+    tokio::fs::rename(&tmp, info_file).await?;
+    Ok(())
+}
+
+/// File name of the n-th preview frame (1-based, as numbered by ffmpeg)
+fn preview_filename(index: usize) -> String {
+    format!("thumb_{:05}.jpg", index)
+}
+
+/// Record frames that ffmpeg has finished writing, up to `frame_count`
+fn collect_frames(
+    cache_dir: &Path,
+    preview_info: &mut VideoPreviewInfo,
+    frame_count: usize,
+) -> bool {
+    let mut added = false;
+
+    for index in preview_info.thumbnails.len() + 1..=frame_count {
+        let filename = preview_filename(index);
+        if !cache_dir.join(&filename).exists() {
+            break;
+        }
+
+        preview_info.thumbnails.push(VideoThumbnail {
+            time: (index - 1) as f64 * preview_info.interval,
+            filename,
+            sprite: None,
+        });
+        added = true;
+    }
+
+    added
+}
+
+/// Extract one frame every `interval` seconds with a single ffmpeg process.
+/// Returns false if the job was cancelled because nobody watched it.
+async fn generate_preview_thumbnails(
+    paths: &PreviewPaths,
+    job: &PreviewJob,
+    preview_info: &mut VideoPreviewInfo,
+) -> Result<bool> {
+    use tokio::io::AsyncBufReadExt;
+
+    let mut command = tokio::process::Command::new("ffmpeg");
+    command
+        .arg("-y")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-nostats");
+
+    // With sparse samples, decoding only keyframes is much faster and close enough
+    if preview_info.interval >= KEYFRAME_ONLY_INTERVAL {
+        command.arg("-skip_frame").arg("nokey");
+    }
+
+    let mut child = command
+        .arg("-i")
+        .arg(&paths.abs_path)
+        .arg("-vf")
+        .arg(format!(
+            "fps=1/{:.3},scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2",
+            preview_info.interval,
+            w = TILE_WIDTH,
+            h = TILE_HEIGHT
+        ))
+        .arg("-q:v")
+        .arg("5")
+        .arg("-progress")
+        .arg("pipe:1")
+        .arg(paths.cache_dir.join("thumb_%05d.jpg"))
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    // Damaged files make ffmpeg write errors continuously; a full pipe would stall it
+    let stderr = drain_stderr(&mut child);
 
-    // Write initial info.json (atomic write to temp file then rename)
-    let init_json = serde_json::to_string_pretty(&preview_info)?;
-    let tmp_info = info_file.with_extension("json.tmp");
-    tokio::fs::write(&tmp_info, init_json).await?;
-    let _ = tokio::fs::rename(&tmp_info, &info_file).await?;
-
-    // Generate thumbnails and update info.json incrementally
-    let mut current_time = 0.0;
-
-    while current_time < duration {
-        let filename = format!("thumb_{:08.2}.jpg", current_time);
-        let thumb_path = cache_dir.join(&filename);
-
-        match generate_video_preview_thumbnail(&abs_path, &thumb_path, current_time).await {
-            Ok(_) => {
-                preview_info.thumbnails.push(VideoThumbnail {
-                    time: current_time,
-                    filename: filename.clone(),
-                });
-
-                // write progress to info.json (atomic)
-                let info_json = serde_json::to_string_pretty(&preview_info)?;
-                let tmp = info_file.with_extension("json.tmp");
-                if let Err(e) = tokio::fs::write(&tmp, info_json).await {
-                    tracing::warn!("Falha ao escrever info temporário: {}", e);
-                } else if let Err(e) = tokio::fs::rename(&tmp, &info_file).await {
-                    tracing::warn!("Falha ao renomear info temporário: {}", e);
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("ffmpeg sem stdout"))?;
+    let mut lines = tokio::io::BufReader::new(stdout).lines();
+
+    let mut watch_check = tokio::time::interval(WATCH_CHECK_INTERVAL);
+
+    // `-progress` reports `frame=N` for every block of progress
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => match line? {
+                Some(line) => line,
+                None => break,
+            },
+            _ = watch_check.tick() => {
+                // Dropping the child kills ffmpeg
+                if !job.is_watched() {
+                    return Ok(false);
                 }
+                continue;
             }
-            Err(e) => {
-                tracing::warn!(
-                    "Falha ao gerar miniatura para tempo {}: {}",
-                    current_time,
-                    e
-                );
+        };
+
+        let frame_count = match line.strip_prefix("frame=") {
+            Some(value) => value.trim().parse::<usize>().unwrap_or(0),
+            None => continue,
+        };
+
+        if collect_frames(&paths.cache_dir, preview_info, frame_count) {
+            job.publish(preview_info);
+            if let Err(e) = write_info(&paths.info_file, preview_info).await {
+                tracing::warn!("Falha ao escrever info temporário: {}", e);
             }
         }
+    }
+
+    let status = child.wait().await?;
+
+    // Pick up the last frames written after the final progress report
+    collect_frames(&paths.cache_dir, preview_info, usize::MAX);
+
+    if !status.success() {
+        let stderr = stderr.await.unwrap_or_default();
+        anyhow::bail!("ffmpeg falhou: {}", stderr);
+    }
+
+    Ok(true)
+}
+
+/// Record scene thumbnails that ffmpeg has finished writing
+fn collect_scenes(cache_dir: &Path, preview_info: &mut VideoPreviewInfo, times: &[f64]) -> bool {
+    let mut added = false;
+
+    for (index, &time) in times.iter().enumerate().skip(preview_info.scenes.len()) {
+        let filename = format!("scene_{:03}.jpg", index + 1);
+        if !cache_dir.join(&filename).exists() {
+            break;
+        }
 
-        current_time += interval;
+        preview_info.scenes.push(VideoScene { time, filename });
+        added = true;
     }
 
-    Ok(Some(preview_info))
+    added
 }
 
-/// Get video duration using ffprobe
-async fn get_video_duration(video_path: &Path) -> Result<f64> {
-    let output = tokio::process::Command::new("ffprobe")
+/// Take a thumbnail at every cut whose scene score exceeds `threshold`.
+/// Returns false if the job was cancelled because nobody watched it.
+async fn generate_scene_thumbnails(
+    paths: &PreviewPaths,
+    job: &PreviewJob,
+    preview_info: &mut VideoPreviewInfo,
+    threshold: f64,
+) -> Result<bool> {
+    use tokio::io::AsyncBufReadExt;
+
+    // `showinfo` logs the timestamp of every selected frame at info level
+    let mut child = tokio::process::Command::new("ffmpeg")
+        .arg("-y")
+        .arg("-hide_banner")
+        .arg("-nostats")
+        .arg("-loglevel")
+        .arg("info")
+        .arg("-i")
+        .arg(&paths.abs_path)
+        .arg("-vf")
+        .arg(format!(
+            "scale={a}:-2,select='gt(scene,{t:.3})',showinfo,scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2",
+            a = SCENE_ANALYSIS_WIDTH,
+            t = threshold,
+            w = TILE_WIDTH,
+            h = TILE_HEIGHT
+        ))
+        .arg("-fps_mode")
+        .arg("vfr")
+        .arg("-frames:v")
+        .arg(MAX_SCENES.to_string())
+        .arg("-q:v")
+        .arg("5")
+        .arg(paths.cache_dir.join("scene_%03d.jpg"))
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("ffmpeg sem stderr"))?;
+    let mut lines = tokio::io::BufReader::new(stderr).lines();
+
+    let mut watch_check = tokio::time::interval(WATCH_CHECK_INTERVAL);
+    let mut times = Vec::new();
+    let mut last_lines = std::collections::VecDeque::new();
+
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => match line? {
+                Some(line) => line,
+                None => break,
+            },
+            _ = watch_check.tick() => {
+                // Dropping the child kills ffmpeg
+                if !job.is_watched() {
+                    return Ok(false);
+                }
+                continue;
+            }
+        };
+
+        let time = line
+            .contains("Parsed_showinfo")
+            .then(|| line.split("pts_time:").nth(1))
+            .flatten()
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|value| value.parse::<f64>().ok());
+
+        match time {
+            Some(time) => {
+                times.push(time);
+                if collect_scenes(&paths.cache_dir, preview_info, &times) {
+                    job.publish(preview_info);
+                }
+            }
+            None => {
+                // Kept for the error message
+                last_lines.push_back(line);
+                if last_lines.len() > 10 {
+                    last_lines.pop_front();
+                }
+            }
+        }
+    }
+
+    let status = child.wait().await?;
+
+    // Pick up the last scenes written after their timestamp was logged
+    collect_scenes(&paths.cache_dir, preview_info, &times);
+
+    if !status.success() {
+        let stderr = Vec::from(last_lines).join("\n");
+        anyhow::bail!("ffmpeg falhou: {}", stderr);
+    }
+
+    Ok(true)
+}
+
+/// Get the path of a scene thumbnail by index
+pub fn get_scene_path(
+    base_dir: &Path,
+    rel_path: &str,
+    index: usize,
+    preview_info: &VideoPreviewInfo,
+) -> Option<PathBuf> {
+    let scene = preview_info.scenes.get(index)?;
+    Some(preview_cache_dir(base_dir, rel_path)?.join(&scene.filename))
+}
+
+/// Read the chapters stored in the container; videos without them (or errors) give an empty list
+async fn probe_chapters(video_path: &Path) -> Vec<VideoChapter> {
+    #[derive(Deserialize)]
+    struct ProbeOutput {
+        #[serde(default)]
+        chapters: Vec<ProbeChapter>,
+    }
+
+    #[derive(Deserialize)]
+    struct ProbeChapter {
+        start_time: String,
+        end_time: String,
+        #[serde(default)]
+        tags: std::collections::HashMap<String, String>,
+    }
+
+    let output = match tokio::process::Command::new("ffprobe")
         .arg("-v")
         .arg("quiet")
-        .arg("-show_entries")
-        .arg("format=duration")
+        .arg("-show_chapters")
         .arg("-of")
-        .arg("csv=p=0")
+        .arg("json")
         .arg(video_path)
         .output()
-        .await?;
+        .await
+    {
+        Ok(output) if output.status.success() => output,
+        Ok(_) | Err(_) => return Vec::new(),
+    };
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("ffprobe falhou: {}", stderr);
+    let probe: ProbeOutput = match serde_json::from_slice(&output.stdout) {
+        Ok(probe) => probe,
+        Err(e) => {
+            tracing::warn!("Falha ao ler capítulos: {}", e);
+            return Vec::new();
+        }
+    };
+
+    probe
+        .chapters
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, chapter)| {
+            let start = chapter.start_time.parse::<f64>().ok()?;
+            let end = chapter.end_time.parse::<f64>().ok()?;
+            // Tag keys are case-sensitive and vary between muxers
+            let title = chapter
+                .tags
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("title"))
+                .map(|(_, value)| value.trim().to_string())
+                .filter(|title| !title.is_empty())
+                .unwrap_or_else(|| format!("Capítulo {}", index + 1));
+            Some(VideoChapter { title, start, end })
+        })
+        .collect()
+}
+
+/// Get or build the thumbnail of a chapter, taken a few seconds into it
+pub async fn get_or_build_chapter_thumbnail(
+    base_dir: &Path,
+    rel_path: &str,
+    index: usize,
+    preview_info: &VideoPreviewInfo,
+) -> Result<Option<PathBuf>> {
+    let chapter = match preview_info.chapters.as_ref().and_then(|c| c.get(index)) {
+        Some(chapter) => chapter,
+        None => return Ok(None),
+    };
+    let paths = match PreviewPaths::resolve(base_dir, rel_path)? {
+        Some(paths) => paths,
+        None => return Ok(None),
+    };
+
+    let thumb_path = paths
+        .cache_dir
+        .join(format!("chapter_{:03}.jpg", index + 1));
+    if thumb_path.exists() {
+        return Ok(Some(thumb_path));
     }
 
-    let duration_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    let duration: f64 = duration_str
-        .parse()
-        .map_err(|e| anyhow::anyhow!("Falha ao parsear duração: {}", e))?;
+    let lock_key = format!("{}-{}", paths.cache_key, index);
+    let lock = CHAPTER_LOCKS
+        .entry(lock_key)
+        .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(())))
+        .clone();
 
-    Ok(duration)
+    let _guard = lock.lock().await;
+
+    if thumb_path.exists() {
+        return Ok(Some(thumb_path));
+    }
+
+    tokio::fs::create_dir_all(&paths.cache_dir).await?;
+
+    let _permit = workers::acquire(Pool::Ffmpeg, Priority::Visible).await;
+
+    let length = (chapter.end - chapter.start).max(0.0);
+    let time = chapter.start + CHAPTER_THUMB_OFFSET.min(length / 2.0);
+    let tmp_path = paths
+        .cache_dir
+        .join(format!("chapter_{:03}.tmp.jpg", index + 1));
+
+    extract_tile(&paths.abs_path, time, TILE_WIDTH, TILE_HEIGHT, &tmp_path).await?;
+
+    tokio::fs::rename(&tmp_path, &thumb_path).await?;
+
+    Ok(Some(thumb_path))
 }
 
-/// Generate a single video thumbnail at specific time
-async fn generate_video_preview_thumbnail(src: &Path, dest: &Path, time: f64) -> Result<()> {
+/// Read a child's stderr as it is written, so ffmpeg never blocks on a full pipe.
+/// Resolves to the last few KB once the process closes it.
+pub fn drain_stderr(child: &mut tokio::process::Child) -> tokio::task::JoinHandle<String> {
+    use tokio::io::AsyncReadExt;
+
+    let stderr = child.stderr.take();
+    tokio::spawn(async move {
+        let mut tail = Vec::new();
+        if let Some(mut stderr) = stderr {
+            let mut buf = [0u8; 4096];
+            while let Ok(read) = stderr.read(&mut buf).await {
+                if read == 0 {
+                    break;
+                }
+                tail.extend_from_slice(&buf[..read]);
+                if tail.len() > STDERR_TAIL_BYTES {
+                    tail.drain(..tail.len() - STDERR_TAIL_BYTES);
+                }
+            }
+        }
+        String::from_utf8_lossy(&tail).into_owned()
+    })
+}
+
+/// Extract the frame at `time` seconds as a JPEG letterboxed to `width`x`height`.
+/// Callers hold an ffmpeg worker permit; nothing is left at `dest` on failure.
+pub async fn extract_tile(
+    video_path: &Path,
+    time: f64,
+    width: u32,
+    height: u32,
+    dest: &Path,
+) -> Result<()> {
     let output = tokio::process::Command::new("ffmpeg")
         .arg("-y")
+        .arg("-loglevel")
+        .arg("error")
         .arg("-ss")
-        .arg(time.to_string())
+        .arg(format!("{:.3}", time))
         .arg("-i")
-        .arg(src)
+        .arg(video_path)
         .arg("-frames:v")
         .arg("1")
         .arg("-vf")
-        .arg("scale=160:90:force_original_aspect_ratio=decrease,pad=160:90:(ow-iw)/2:(oh-ih)/2")
+        .arg(format!(
+            "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2",
+            w = width,
+            h = height
+        ))
         .arg("-q:v")
         .arg("5")
         .arg(dest)
@@ -190,6 +953,7 @@ async fn generate_video_preview_thumbnail(src: &Path, dest: &Path, time: f64) ->
         .await?;
 
     if !output.status.success() {
+        let _ = tokio::fs::remove_file(dest).await;
         let stderr = String::from_utf8_lossy(&output.stderr);
         anyhow::bail!("ffmpeg falhou: {}", stderr);
     }
@@ -197,6 +961,62 @@ async fn generate_video_preview_thumbnail(src: &Path, dest: &Path, time: f64) ->
     Ok(())
 }
 
+/// Get video duration using ffprobe
+pub async fn get_video_duration(video_path: &Path) -> Result<f64> {
+    let output = tokio::process::Command::new("ffprobe")
+        .arg("-v")
+        .arg("quiet")
+        .arg("-show_entries")
+        .arg("format=duration")
+        .arg("-of")
+        .arg("csv=p=0")
+        .arg(video_path)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffprobe falhou: {}", stderr);
+    }
+
+    let duration_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let duration: f64 = duration_str
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Falha ao parsear duração: {}", e))?;
+
+    Ok(duration)
+}
+
+/// Cache directory holding the previews of a video
+fn preview_cache_dir(base_dir: &Path, rel_path: &str) -> Option<PathBuf> {
+    let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path).ok()?;
+    let cache_key = blake3::hash(rel_path.as_bytes()).to_string();
+    Some(abs_path.parent()?.join(".video-previews").join(&cache_key))
+}
+
+/// Get the path of a sprite sheet by index
+pub fn get_sprite_path(
+    base_dir: &Path,
+    rel_path: &str,
+    index: usize,
+    preview_info: &VideoPreviewInfo,
+) -> Option<PathBuf> {
+    let sheet = preview_info.sprites.get(index)?;
+    Some(preview_cache_dir(base_dir, rel_path)?.join(&sheet.filename))
+}
+
+/// Get the path of the WebVTT thumbnails track, once the sprites are built
+pub fn get_vtt_path(
+    base_dir: &Path,
+    rel_path: &str,
+    preview_info: &VideoPreviewInfo,
+) -> Option<PathBuf> {
+    if preview_info.sprites.is_empty() {
+        return None;
+    }
+    Some(preview_cache_dir(base_dir, rel_path)?.join(VTT_FILENAME))
+}
+
 /// Get preview thumbnail path for a specific time
 pub fn get_preview_thumbnail_path(
     base_dir: &Path,
@@ -204,9 +1024,7 @@ pub fn get_preview_thumbnail_path(
     target_time: f64,
     preview_info: &VideoPreviewInfo,
 ) -> Option<PathBuf> {
-    let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path).ok()?;
-    let cache_key = blake3::hash(rel_path.as_bytes()).to_string();
-    let cache_dir = abs_path.parent()?.join(".video-previews").join(&cache_key);
+    let cache_dir = preview_cache_dir(base_dir, rel_path)?;
 
     // Find the closest thumbnail
     let mut closest_thumb: Option<&VideoThumbnail> = None;
@@ -0,0 +1,436 @@
+use anyhow::Result;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+use tokio::sync::Mutex;
+
+use crate::models::workers::{self, Pool, Priority};
+
+type WaveformLocks = Arc<DashMap<String, Arc<Mutex<()>>>>;
+
+lazy_static::lazy_static! {
+    static ref WAVEFORM_LOCKS: WaveformLocks = Arc::new(DashMap::new());
+}
+
+/// Peaks kept per file; enough for a full-width player without being a large download
+const PEAK_COUNT: usize = 1000;
+
+/// Samples are first reduced to one peak per window of this many seconds
+const WINDOW_SECONDS: f64 = 0.01;
+
+/// Sample rate asked from ffmpeg when it decodes instead
+const FFMPEG_SAMPLE_RATE: u32 = 8000;
+
+/// Size of the cached PNG rendering
+const PNG_WIDTH: u32 = 1200;
+const PNG_HEIGHT: u32 = 240;
+
+const BACKGROUND: image::Rgb<u8> = image::Rgb([15, 23, 42]);
+const BAR: image::Rgb<u8> = image::Rgb([56, 189, 248]);
+
+/// Amplitude envelope of an audio file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Waveform {
+    /// Decoded length in seconds
+    pub duration: f64,
+    /// Evenly spaced peaks, 0–255 relative to the loudest one
+    pub peaks: Vec<u8>,
+}
+
+/// Cached files of a waveform
+struct WaveformPaths {
+    abs_path: PathBuf,
+    key: String,
+    json: PathBuf,
+    png: PathBuf,
+}
+
+impl WaveformPaths {
+    /// `None` for files that are not audio
+    async fn resolve(base_dir: &Path, rel_path: &str) -> Result<Option<Self>> {
+        let abs_path = crate::models::fs::canonicalize_in_base(base_dir, rel_path)?;
+
+        let (_, media_kind) = crate::models::media::detect(&abs_path);
+        if !matches!(media_kind, crate::models::media::MediaKind::Audio) {
+            return Ok(None);
+        }
+
+        // Version by size and mtime so a replaced file gets a new waveform
+        let metadata = tokio::fs::metadata(&abs_path).await?;
+        let modified = metadata
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let key = format!(
+            "{}-{:x}-{:x}",
+            blake3::hash(rel_path.as_bytes()),
+            metadata.len(),
+            modified
+        );
+        let cache_dir = base_dir.join(".media-serve").join("waveforms");
+
+        Ok(Some(Self {
+            json: cache_dir.join(format!("{}.json", key)),
+            png: cache_dir.join(format!("{}.png", key)),
+            abs_path,
+            key,
+        }))
+    }
+}
+
+/// Get or compute the waveform of an audio file.
+/// Decodes in-process when the format is supported, with ffmpeg as the fallback.
+/// Returns `None` for files that are not audio.
+pub async fn get_or_build(
+    base_dir: &Path,
+    rel_path: &str,
+    ffmpeg_available: bool,
+    priority: Priority,
+) -> Result<Option<Waveform>> {
+    let paths = match WaveformPaths::resolve(base_dir, rel_path).await? {
+        Some(paths) => paths,
+        None => return Ok(None),
+    };
+    get_or_build_at(&paths, ffmpeg_available, priority)
+        .await
+        .map(Some)
+}
+
+/// Get or render the PNG image of a waveform
+pub async fn get_or_build_png(
+    base_dir: &Path,
+    rel_path: &str,
+    ffmpeg_available: bool,
+) -> Result<Option<PathBuf>> {
+    let paths = match WaveformPaths::resolve(base_dir, rel_path).await? {
+        Some(paths) => paths,
+        None => return Ok(None),
+    };
+    if paths.png.exists() {
+        return Ok(Some(paths.png));
+    }
+
+    let waveform = get_or_build_at(&paths, ffmpeg_available, Priority::Visible).await?;
+
+    let lock = WAVEFORM_LOCKS
+        .entry(format!("{}.png", paths.key))
+        .or_insert_with(|| Arc::new(Mutex::new(())))
+        .clone();
+
+    let _guard = lock.lock().await;
+
+    if paths.png.exists() {
+        return Ok(Some(paths.png));
+    }
+
+    let _permit = workers::acquire(Pool::Image, Priority::Visible).await;
+    let dest = paths.png.clone();
+    tokio::task::spawn_blocking(move || {
+        let tmp = dest.with_extension("tmp.png");
+        render(&waveform.peaks, PNG_WIDTH, PNG_HEIGHT)
+            .save_with_format(&tmp, image::ImageFormat::Png)?;
+        std::fs::rename(&tmp, &dest)?;
+        anyhow::Ok(())
+    })
+    .await??;
+
+    Ok(Some(paths.png))
+}
+
+async fn get_or_build_at(
+    paths: &WaveformPaths,
+    ffmpeg_available: bool,
+    priority: Priority,
+) -> Result<Waveform> {
+    if let Some(waveform) = read_cached(&paths.json).await {
+        return Ok(waveform);
+    }
+
+    let lock = WAVEFORM_LOCKS
+        .entry(paths.key.clone())
+        .or_insert_with(|| Arc::new(Mutex::new(())))
+        .clone();
+
+    let _guard = lock.lock().await;
+
+    if let Some(waveform) = read_cached(&paths.json).await {
+        return Ok(waveform);
+    }
+
+    // Decoding a whole file takes as long as an ffmpeg job, so it queues with them
+    // instead of holding up image thumbnails
+    let decoded = {
+        let _permit = workers::acquire(Pool::Ffmpeg, priority).await;
+        let src = paths.abs_path.clone();
+        tokio::task::spawn_blocking(move || decode_windows(&src)).await?
+    };
+
+    let windows = match decoded {
+        Ok(windows) if windows.frames > 0 => windows,
+        result if ffmpeg_available => {
+            if let Err(e) = result {
+                tracing::debug!("Decoding {:?} with ffmpeg instead: {}", paths.abs_path, e);
+            }
+            let _permit = workers::acquire(Pool::Ffmpeg, priority).await;
+            decode_windows_ffmpeg(&paths.abs_path).await?
+        }
+        result => result?,
+    };
+
+    let waveform = windows.into_waveform();
+
+    if let Some(dir) = paths.json.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let tmp = paths.json.with_extension("json.tmp");
+    tokio::fs::write(&tmp, serde_json::to_vec(&waveform)?).await?;
+    tokio::fs::rename(&tmp, &paths.json).await?;
+
+    Ok(waveform)
+}
+
+async fn read_cached(path: &Path) -> Option<Waveform> {
+    let content = tokio::fs::read(path).await.ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+/// Peak amplitude of every short window of a stream
+struct Windows {
+    sample_rate: u32,
+    window: usize,
+    /// Frames in the current window and its peak so far
+    filled: usize,
+    current: f32,
+    frames: u64,
+    peaks: Vec<f32>,
+}
+
+impl Windows {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            window: ((sample_rate as f64 * WINDOW_SECONDS) as usize).max(1),
+            filled: 0,
+            current: 0.0,
+            frames: 0,
+            peaks: Vec::new(),
+        }
+    }
+
+    /// Add one frame, given its loudest channel
+    fn push(&mut self, amplitude: f32) {
+        self.current = self.current.max(amplitude);
+        self.filled += 1;
+        self.frames += 1;
+        if self.filled == self.window {
+            self.peaks.push(self.current);
+            self.filled = 0;
+            self.current = 0.0;
+        }
+    }
+
+    /// Merge the windows into `PEAK_COUNT` peaks scaled to the loudest one
+    fn into_waveform(mut self) -> Waveform {
+        if self.filled > 0 {
+            self.peaks.push(self.current);
+        }
+
+        let count = self.peaks.len().min(PEAK_COUNT);
+        let mut peaks = vec![0.0f32; count];
+        for (index, peak) in self.peaks.iter().enumerate() {
+            let bucket = index * count / self.peaks.len();
+            peaks[bucket] = peaks[bucket].max(*peak);
+        }
+
+        let loudest = peaks.iter().cloned().fold(0.0f32, f32::max);
+        let scale = if loudest > 0.0 { 255.0 / loudest } else { 0.0 };
+
+        Waveform {
+            duration: self.frames as f64 / self.sample_rate as f64,
+            peaks: peaks
+                .into_iter()
+                .map(|peak| (peak * scale).round().clamp(0.0, 255.0) as u8)
+                .collect(),
+        }
+    }
+}
+
+/// Decode with Symphonia, in-process
+fn decode_windows(path: &Path) -> Result<Windows> {
+    use symphonia::core::audio::SampleBuffer;
+    use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+    use symphonia::core::errors::Error;
+    use symphonia::core::formats::FormatOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::meta::MetadataOptions;
+    use symphonia::core::probe::Hint;
+
+    let file = std::fs::File::open(path)?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        stream,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let mut format = probed.format;
+
+    // Cover art can show up as a track of its own
+    let track = format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| anyhow::anyhow!("no audio track"))?;
+    let track_id = track.id;
+    let sample_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| anyhow::anyhow!("unknown sample rate"))?;
+
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+    let mut windows = Windows::new(sample_rate);
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A damaged packet only leaves a gap
+            Err(Error::DecodeError(_)) => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let needed = decoded.capacity() * channels;
+        if buffer.as_ref().is_none_or(|b| b.capacity() < needed) {
+            buffer = Some(SampleBuffer::new(decoded.capacity() as u64, spec));
+        }
+        let buffer = buffer.as_mut().unwrap();
+        buffer.copy_interleaved_ref(decoded);
+
+        for frame in buffer.samples().chunks(channels) {
+            windows.push(frame.iter().fold(0.0f32, |peak, s| peak.max(s.abs())));
+        }
+    }
+
+    Ok(windows)
+}
+
+/// Decode with ffmpeg to mono 32-bit floats, for formats Symphonia does not handle
+async fn decode_windows_ffmpeg(path: &Path) -> Result<Windows> {
+    let mut child = tokio::process::Command::new("ffmpeg")
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-i")
+        .arg(path)
+        .arg("-vn")
+        .arg("-ac")
+        .arg("1")
+        .arg("-ar")
+        .arg(FFMPEG_SAMPLE_RATE.to_string())
+        .arg("-f")
+        .arg("f32le")
+        .arg("pipe:1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stderr = crate::models::video_previews::drain_stderr(&mut child);
+    let mut stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("ffmpeg stdout unavailable"))?;
+
+    let mut windows = Windows::new(FFMPEG_SAMPLE_RATE);
+    let mut chunk = vec![0u8; 64 * 1024];
+    // Bytes of a sample split across two reads
+    let mut pending = Vec::with_capacity(4);
+
+    loop {
+        let read = stdout.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        let mut bytes = &chunk[..read];
+
+        if !pending.is_empty() {
+            let missing = (4 - pending.len()).min(bytes.len());
+            pending.extend_from_slice(&bytes[..missing]);
+            bytes = &bytes[missing..];
+            if pending.len() < 4 {
+                continue;
+            }
+            windows
+                .push(f32::from_le_bytes([pending[0], pending[1], pending[2], pending[3]]).abs());
+            pending.clear();
+        }
+
+        let mut samples = bytes.chunks_exact(4);
+        for sample in &mut samples {
+            windows.push(f32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]).abs());
+        }
+        pending.extend_from_slice(samples.remainder());
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        let stderr = stderr.await.unwrap_or_default();
+        anyhow::bail!("ffmpeg failed: {}", stderr);
+    }
+
+    Ok(windows)
+}
+
+/// Draw the peaks as mirrored bars, one per column
+pub fn render(peaks: &[u8], width: u32, height: u32) -> image::RgbImage {
+    let mut img = image::RgbImage::from_pixel(width, height, BACKGROUND);
+    if peaks.is_empty() {
+        return img;
+    }
+
+    let middle = height as f32 / 2.0;
+    for x in 0..width {
+        // Loudest peak covered by this column
+        let start = x as usize * peaks.len() / width as usize;
+        let end = ((x as usize + 1) * peaks.len() / width as usize).max(start + 1);
+        let peak = peaks[start..end.min(peaks.len())]
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(0);
+
+        // Silence still shows as a thin line
+        let half = (peak as f32 / 255.0 * (middle - 1.0)).max(0.5);
+        let top = (middle - half).floor().max(0.0) as u32;
+        let bottom = ((middle + half).ceil() as u32).min(height);
+        for y in top..bottom {
+            img.put_pixel(x, y, BAR);
+        }
+    }
+
+    img
+}
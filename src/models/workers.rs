@@ -0,0 +1,196 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// How urgently a job should get a worker slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// Requested by a browser for a tile that is on screen
+    Visible,
+    /// Pre-generation work that can wait for the server to be idle
+    Background,
+}
+
+/// Which pool a job runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pool {
+    /// In-process image decoding/resizing (`spawn_blocking`)
+    Image,
+    /// External ffmpeg processes, and in-process decoding of whole audio files
+    Ffmpeg,
+    /// Animated hover previews, kept apart so they never delay static thumbnails
+    Hover,
+}
+
+/// A counting limiter that hands free slots to visible jobs before background ones
+struct Limiter {
+    state: Mutex<LimiterState>,
+}
+
+struct LimiterState {
+    capacity: usize,
+    active: usize,
+    visible: VecDeque<oneshot::Sender<()>>,
+    background: VecDeque<oneshot::Sender<()>>,
+}
+
+impl Limiter {
+    fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(LimiterState {
+                capacity: capacity.max(1),
+                active: 0,
+                visible: VecDeque::new(),
+                background: VecDeque::new(),
+            }),
+        }
+    }
+
+    fn set_capacity(&self, capacity: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.capacity = capacity.max(1);
+    }
+
+    fn try_enqueue(&self, priority: Priority) -> Option<oneshot::Receiver<()>> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.active < state.capacity {
+            state.active += 1;
+            return None;
+        }
+
+        let (tx, rx) = oneshot::channel();
+        match priority {
+            Priority::Visible => state.visible.push_back(tx),
+            Priority::Background => state.background.push_back(tx),
+        }
+        Some(rx)
+    }
+
+    /// Hand the slot to the next waiter, or free it if nobody is waiting
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        // Capacity may have been lowered while jobs were running
+        if state.active > state.capacity {
+            state.active -= 1;
+            return;
+        }
+
+        while let Some(tx) = state
+            .visible
+            .pop_front()
+            .or_else(|| state.background.pop_front())
+        {
+            // A closed receiver means the request was dropped, try the next one
+            if tx.send(()).is_ok() {
+                return;
+            }
+        }
+
+        state.active -= 1;
+    }
+
+    fn visible_waiting(&self) -> usize {
+        self.state.lock().unwrap().visible.len()
+    }
+}
+
+/// Slot in a worker pool, released on drop
+pub struct Permit {
+    limiter: &'static Limiter,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.limiter.release();
+    }
+}
+
+/// Pending acquisition; returns the slot if it was handed over after the caller gave up
+struct Waiter {
+    limiter: &'static Limiter,
+    rx: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        if let Some(mut rx) = self.rx.take() {
+            rx.close();
+            if rx.try_recv().is_ok() {
+                self.limiter.release();
+            }
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref IMAGE_POOL: Limiter = Limiter::new(default_image_workers());
+    static ref FFMPEG_POOL: Limiter = Limiter::new(default_ffmpeg_workers());
+    static ref HOVER_POOL: Limiter = Limiter::new(1);
+    static ref STARTED: Instant = Instant::now();
+    static ref LAST_VISIBLE_MS: AtomicU64 = AtomicU64::new(0);
+}
+
+/// Default number of concurrent image jobs (one per CPU)
+pub fn default_image_workers() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(2)
+}
+
+/// Default number of concurrent ffmpeg processes
+pub fn default_ffmpeg_workers() -> usize {
+    2
+}
+
+/// Set pool sizes from the app configuration
+pub fn configure(image_workers: usize, ffmpeg_workers: usize) {
+    IMAGE_POOL.set_capacity(image_workers);
+    FFMPEG_POOL.set_capacity(ffmpeg_workers);
+    tracing::info!(
+        "Thumbnail workers: {} image, {} ffmpeg",
+        image_workers.max(1),
+        ffmpeg_workers.max(1)
+    );
+}
+
+fn limiter(pool: Pool) -> &'static Limiter {
+    match pool {
+        Pool::Image => &IMAGE_POOL,
+        Pool::Ffmpeg => &FFMPEG_POOL,
+        Pool::Hover => &HOVER_POOL,
+    }
+}
+
+/// Wait for a free slot in the given pool
+pub async fn acquire(pool: Pool, priority: Priority) -> Permit {
+    let limiter = limiter(pool);
+
+    if priority == Priority::Visible {
+        LAST_VISIBLE_MS.store(STARTED.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    if let Some(rx) = limiter.try_enqueue(priority) {
+        let mut waiter = Waiter {
+            limiter,
+            rx: Some(rx),
+        };
+        if let Some(rx) = waiter.rx.as_mut() {
+            // The sender is only dropped after a successful hand-over
+            let _ = rx.await;
+        }
+        waiter.rx = None;
+    }
+
+    Permit { limiter }
+}
+
+/// Whether no visible work has been requested for at least `quiet`
+pub fn is_idle(quiet: Duration) -> bool {
+    let last = Duration::from_millis(LAST_VISIBLE_MS.load(Ordering::Relaxed));
+    let waiting = IMAGE_POOL.visible_waiting() + FFMPEG_POOL.visible_waiting();
+    waiting == 0 && STARTED.elapsed().saturating_sub(last) >= quiet
+}